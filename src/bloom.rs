@@ -0,0 +1,78 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A fixed-size bloom filter used to remember visited URLs in low-memory mode.
+///
+/// Lookups may return false positives (an unseen item reported as seen), but never
+/// false negatives. With the default size, the false positive rate stays below 1%
+/// for roughly 100,000 items.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl Default for BloomFilter {
+    fn default() -> Self {
+        // 2^20 bits (128 KiB) and 7 hash functions.
+        Self::new(1 << 20, 7)
+    }
+}
+
+impl BloomFilter {
+    /// Creates a filter with `num_bits` bits (rounded up to a multiple of 64) and
+    /// `num_hashes` hash functions.
+    pub fn new(num_bits: usize, num_hashes: u32) -> Self {
+        let words = num_bits.div_ceil(64).max(1);
+        Self {
+            bits: vec![0; words],
+            num_bits: (words * 64) as u64,
+            num_hashes: num_hashes.max(1),
+        }
+    }
+
+    /// Inserts an item. Returns `true` if the item was (definitely) not present before.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        let mut newly_set = false;
+        for bit in self.bit_indexes(item) {
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                newly_set = true;
+            }
+        }
+        newly_set
+    }
+
+    /// Returns `true` if the item may have been inserted before.
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.bit_indexes(item)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1u64 << (bit % 64)) != 0)
+    }
+
+    fn bit_indexes<T: Hash + ?Sized>(&self, item: &T) -> impl Iterator<Item = u64> + use<T> {
+        // Double hashing: h1 + i * h2 simulates `num_hashes` independent hash functions.
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let h1 = hasher.finish();
+        0xb10f_u16.hash(&mut hasher);
+        let h2 = hasher.finish() | 1;
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BloomFilter;
+
+    #[test]
+    fn test_bloom_filter_insert_and_contains() {
+        let mut bloom = BloomFilter::default();
+        assert!(bloom.insert("http://example.com/a/"));
+        assert!(!bloom.insert("http://example.com/a/"));
+        assert!(bloom.contains("http://example.com/a/"));
+        assert!(!bloom.contains("http://example.com/b/"));
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{Result, bail};
+
+/// Headers that describe one particular request or connection rather than the
+/// session, and are left to the client.
+const SKIPPED_HEADERS: [&str; 5] =
+    ["host", "content-length", "connection", "accept-encoding", "transfer-encoding"];
+
+/// The headers of a browser request, for `--from-browser`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BrowserSession {
+    /// `Name: value` headers, cookies included as a `Cookie` header.
+    pub headers: Vec<String>,
+    /// Parts of the input that were skipped.
+    pub warnings: Vec<String>,
+}
+
+impl BrowserSession {
+    fn push(&mut self, name: &str, value: &str) {
+        let name = name.trim();
+        if name.starts_with(':') || SKIPPED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            return;
+        }
+        self.headers.push(format!("{}: {}", name, value.trim()));
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.headers.iter().any(|header| {
+            header
+                .split_once(':')
+                .is_some_and(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+        })
+    }
+}
+
+/// Parses a request copied from the browser developer tools, either as a `curl`
+/// command ("Copy as cURL", bash syntax) or as a HAR export. Of a HAR file with several
+/// entries, the first one is used.
+pub fn parse(content: &str) -> Result<BrowserSession> {
+    let content = content.trim();
+    let session = if content.starts_with('{') {
+        parse_har(content)?
+    } else if content.starts_with("curl") {
+        parse_curl(content)?
+    } else {
+        bail!("Expected a curl command (\"Copy as cURL\") or a HAR file.");
+    };
+    if session.headers.is_empty() {
+        bail!("The browser request contains no headers or cookies to reuse.");
+    }
+    Ok(session)
+}
+
+fn parse_curl(command: &str) -> Result<BrowserSession> {
+    let mut session = BrowserSession::default();
+    let arguments = split_shell_words(command)?;
+    let mut arguments = arguments.iter().skip(1);
+    while let Some(argument) = arguments.next() {
+        let (option, inline_value) = match argument.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => (option, Some(value.to_string())),
+            _ => (argument.as_str(), None),
+        };
+        let takes_value = matches!(
+            option,
+            "-H" | "--header" | "-b" | "--cookie" | "-A" | "--user-agent" | "-e" | "--referer"
+        );
+        if !takes_value {
+            continue;
+        }
+        let Some(value) = inline_value.or_else(|| arguments.next().cloned()) else {
+            session.warnings.push(format!("{} has no value.", option));
+            continue;
+        };
+        match option {
+            "-H" | "--header" => match value.split_once(':') {
+                Some((name, value)) if !name.trim().is_empty() => session.push(name, value),
+                _ => session.warnings.push(format!("Skipped invalid header '{}'.", value)),
+            },
+            "-b" | "--cookie" if value.contains('=') => session.push("Cookie", &value),
+            "-b" | "--cookie" => session
+                .warnings
+                .push(format!("Skipped cookie file '{}'; only inline cookies are read.", value)),
+            "-A" | "--user-agent" => session.push("User-Agent", &value),
+            _ => session.push("Referer", &value),
+        }
+    }
+    Ok(session)
+}
+
+/// Splits a command line like bash does for the quoting that browsers emit: single
+/// quotes, double quotes, `$'...'` strings and backslash line continuations.
+fn split_shell_words(command: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '\\' => match chars.next() {
+                Some('\n') | Some('\r') | None => {}
+                Some(escaped) => word.get_or_insert_default().push(escaped),
+            },
+            '\'' => {
+                let quoted = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => quoted.push(c),
+                        None => bail!("Unterminated ' quote in the curl command."),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                let quoted = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => quoted.push('\n'),
+                            Some('t') => quoted.push('\t'),
+                            Some('r') => quoted.push('\r'),
+                            Some(escaped) => quoted.push(escaped),
+                            None => bail!("Unterminated $' quote in the curl command."),
+                        },
+                        Some(c) => quoted.push(c),
+                        None => bail!("Unterminated $' quote in the curl command."),
+                    }
+                }
+            }
+            '"' => {
+                let quoted = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            quoted.extend(chars.next());
+                        }
+                        Some(c) => quoted.push(c),
+                        None => bail!("Unterminated \" quote in the curl command."),
+                    }
+                }
+            }
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn parse_har(content: &str) -> Result<BrowserSession> {
+    let har: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| anyhow::anyhow!("Invalid HAR file: {}", e))?;
+    let mut session = BrowserSession::default();
+    // A whole archive, or a single entry copied from it.
+    let request = match har["log"]["entries"].as_array() {
+        Some(entries) => {
+            if entries.len() > 1 {
+                session
+                    .warnings
+                    .push(format!("Using the first of {} HAR entries.", entries.len()));
+            }
+            entries.first().map(|entry| &entry["request"])
+        }
+        None => har.get("request"),
+    };
+    let Some(request) = request else {
+        bail!("The HAR file contains no request.");
+    };
+
+    for header in request["headers"].as_array().into_iter().flatten() {
+        match (header["name"].as_str(), header["value"].as_str()) {
+            (Some(name), Some(value)) => session.push(name, value),
+            _ => session.warnings.push(format!("Skipped invalid HAR header {}.", header)),
+        }
+    }
+    if !session.has("cookie") {
+        let cookies: Vec<String> = request["cookies"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|cookie| {
+                Some(format!("{}={}", cookie["name"].as_str()?, cookie["value"].as_str()?))
+            })
+            .collect();
+        if !cookies.is_empty() {
+            session.push("Cookie", &cookies.join("; "));
+        }
+    }
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn test_parse_copied_curl_command() {
+        let session = parse(
+            "curl 'https://example.com/app' \\\n  -H 'accept: text/html' \\\n  -H $'x-token: it\\'s' \\\n  -H 'accept-encoding: gzip' \\\n  -b 'session=abc; theme=dark' \\\n  -H \"broken\" \\\n  --compressed",
+        )
+        .unwrap();
+        assert_eq!(
+            session.headers,
+            ["accept: text/html", "x-token: it's", "Cookie: session=abc; theme=dark"]
+        );
+        assert_eq!(session.warnings, ["Skipped invalid header 'broken'."]);
+    }
+
+    #[test]
+    fn test_parse_har_entry_with_cookies() {
+        let session = parse(
+            r#"{"log": {"entries": [{"request": {
+                "headers": [{"name": ":authority", "value": "example.com"},
+                            {"name": "Authorization", "value": "Bearer t"}],
+                "cookies": [{"name": "session", "value": "abc"}, {"name": "id", "value": "7"}]
+            }}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(session.headers, ["Authorization: Bearer t", "Cookie: session=abc; id=7"]);
+        assert!(parse("not a request").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use anyhow::{Result, bail};
+
+/// Words generated from a character set instead of read from a wordlist, for
+/// brute-forcing short tokens and IDs (`--charset`): every string of `min_len` to
+/// `max_len` characters, shortest first and in charset order within a length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Charset {
+    chars: Vec<char>,
+    min_len: usize,
+    max_len: usize,
+}
+
+impl Charset {
+    /// A generator for the distinct characters of `chars`, in order of first appearance.
+    pub fn new(chars: &str, min_len: usize, max_len: usize) -> Result<Self> {
+        let mut distinct = Vec::new();
+        for c in chars.chars() {
+            if !distinct.contains(&c) {
+                distinct.push(c);
+            }
+        }
+        if distinct.is_empty() {
+            bail!("The charset is empty.");
+        }
+        if min_len == 0 || min_len > max_len {
+            bail!("Invalid length {}-{}: expected 1 <= MIN <= MAX.", min_len, max_len);
+        }
+        Ok(Charset {
+            chars: distinct,
+            min_len,
+            max_len,
+        })
+    }
+
+    /// The number of generated words, saturating at `usize::MAX`.
+    pub fn count(&self) -> usize {
+        (self.min_len..=self.max_len)
+            .map(|len| self.chars.len().saturating_pow(len as u32))
+            .fold(0, usize::saturating_add)
+    }
+
+    /// Generates the words one by one, without holding them in memory.
+    pub fn words(&self) -> Words {
+        Words {
+            chars: self.chars.clone(),
+            indexes: vec![0; self.min_len],
+            max_len: self.max_len,
+        }
+    }
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chars: String = self.chars.iter().collect();
+        write!(f, "'{}' with length {}-{}", chars, self.min_len, self.max_len)
+    }
+}
+
+/// The words of a [`Charset`], counting up like an odometer.
+#[derive(Debug, Clone)]
+pub struct Words {
+    chars: Vec<char>,
+    /// The charset positions of the next word; longer than `max_len` when done.
+    indexes: Vec<usize>,
+    max_len: usize,
+}
+
+impl Iterator for Words {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.indexes.len() > self.max_len {
+            return None;
+        }
+        let word = self.indexes.iter().map(|&i| self.chars[i]).collect();
+        // Advance the rightmost position that can be, resetting the ones after it; when
+        // every position wraps around, continue with the next length.
+        match self.indexes.iter().rposition(|&i| i + 1 < self.chars.len()) {
+            Some(position) => {
+                self.indexes[position] += 1;
+                self.indexes[position + 1..].fill(0);
+            }
+            None => self.indexes = vec![0; self.indexes.len() + 1],
+        }
+        Some(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Charset;
+
+    #[test]
+    fn test_charset_generates_every_combination() {
+        let charset = Charset::new("ab", 1, 2).unwrap();
+        let words: Vec<String> = charset.words().collect();
+        assert_eq!(words, ["a", "b", "aa", "ab", "ba", "bb"]);
+        assert_eq!(charset.count(), words.len());
+
+        for (chars, min_len, max_len, expected) in
+            [("abc123", 1, 3, 6 + 36 + 216), ("0123456789", 4, 4, 10_000), ("xx", 3, 3, 1)]
+        {
+            let charset = Charset::new(chars, min_len, max_len).unwrap();
+            assert_eq!(charset.count(), expected);
+            assert_eq!(charset.words().count(), expected);
+        }
+        assert_eq!(Charset::new("ab", 1, 200).unwrap().count(), usize::MAX);
+        assert!(Charset::new("", 1, 2).is_err());
+        assert!(Charset::new("ab", 3, 2).is_err());
+    }
+}
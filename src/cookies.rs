@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, HashMap};
+
+use reqwest::header::{HeaderMap, SET_COOKIE};
+use tokio::sync::Mutex;
+
+/// A minimal cookie jar shared by all requests of a scan, so a session cookie that
+/// the server rotates on every response is sent with its latest value.
+///
+/// Cookies are kept per host and sent to every path of it. Of the cookie attributes,
+/// only `Max-Age=0` (deletion) is honored; `Domain`, `Path`, `Expires` and `Secure`
+/// are ignored.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    hosts: Mutex<HashMap<String, BTreeMap<String, String>>>,
+}
+
+impl CookieJar {
+    /// Stores the cookies of the `Set-Cookie` headers of a response from `url`.
+    pub async fn store(&self, url: &url::Url, headers: &HeaderMap) {
+        let Some(host) = url.host_str() else {
+            return;
+        };
+        let set_cookies: Vec<String> = headers
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok().map(String::from))
+            .collect();
+        if set_cookies.is_empty() {
+            return;
+        }
+        let mut hosts = self.hosts.lock().await;
+        let cookies = hosts.entry(host.to_string()).or_default();
+        for set_cookie in set_cookies {
+            let mut parts = set_cookie.split(';');
+            let Some((name, value)) = parts.next().and_then(|pair| pair.split_once('=')) else {
+                continue;
+            };
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            let deleted = parts.any(|attribute| {
+                attribute
+                    .split_once('=')
+                    .is_some_and(|(key, max_age)| {
+                        key.trim().eq_ignore_ascii_case("max-age")
+                            && max_age.trim().parse::<i64>().is_ok_and(|secs| secs <= 0)
+                    })
+            });
+            if deleted {
+                cookies.remove(name);
+            } else {
+                cookies.insert(name.to_string(), value.trim().to_string());
+            }
+        }
+    }
+
+    /// The value of the `Cookie` header for a request to `url`, if any cookies are set.
+    pub async fn header_value(&self, url: &url::Url) -> Option<String> {
+        let hosts = self.hosts.lock().await;
+        let cookies = hosts.get(url.host_str()?)?;
+        if cookies.is_empty() {
+            return None;
+        }
+        Some(
+            cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CookieJar;
+    use reqwest::header::{HeaderMap, HeaderValue, SET_COOKIE};
+
+    #[tokio::test]
+    async fn test_cookie_jar_keeps_latest_value_per_host() {
+        let jar = CookieJar::default();
+        let url = url::Url::parse("http://example.com/login").unwrap();
+        let other = url::Url::parse("http://other.example/").unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, HeaderValue::from_static("session=1; Path=/; HttpOnly"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("theme=dark"));
+        jar.store(&url, &headers).await;
+        assert_eq!(jar.header_value(&url).await.as_deref(), Some("session=1; theme=dark"));
+        assert_eq!(jar.header_value(&other).await, None);
+
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, HeaderValue::from_static("session=2"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("theme=; Max-Age=0"));
+        jar.store(&url, &headers).await;
+        assert_eq!(jar.header_value(&url).await.as_deref(), Some("session=2"));
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use dirnutek::Finding;
+use serde::Serialize;
+
+use crate::output::{self, ScanResult};
+
+/// The differences between two scan result files, keyed by URL.
+#[derive(Debug, Default, Serialize)]
+pub struct ScanDiff {
+    /// Hits only found by the new scan.
+    pub added: Vec<ScanResult>,
+    /// Hits only found by the old scan.
+    pub removed: Vec<ScanResult>,
+    /// Hits found by both scans with a different status or size.
+    pub changed: Vec<ChangedResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedResult {
+    pub old: ScanResult,
+    pub new: ScanResult,
+}
+
+/// Reads the results of a saved scan output in any `--output-format`: plain lines
+/// (`[200 OK] http://...`), JSON records and CSV rows. Comment lines (`# ...`), the CSV
+/// header and anything else without a URL are skipped.
+pub async fn load_results(path: &Path) -> Result<Vec<ScanResult>> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+    Ok(content.lines().map(str::trim).filter_map(parse_result).collect())
+}
+
+/// Parses one line of a saved scan output. Structured results are shown as plain lines.
+fn parse_result(line: &str) -> Option<ScanResult> {
+    let result = if line.starts_with('[') {
+        ScanResult::parse(line.to_string())
+    } else {
+        let finding = if line.starts_with('{') {
+            serde_json::from_str::<Finding>(line).ok()?
+        } else {
+            output::parse_csv_row(line)?
+        };
+        ScanResult::new(finding.to_string(), &finding)
+    };
+    (!result.url.is_empty()).then_some(result)
+}
+
+/// Compares two scans. Results are matched by URL and ordered by URL. A size change is
+/// only reported if both sizes are known, so a scan with `--head-first` can be compared
+/// to one without.
+pub fn diff_results(old: Vec<ScanResult>, new: Vec<ScanResult>) -> ScanDiff {
+    let mut old: BTreeMap<String, ScanResult> =
+        old.into_iter().map(|result| (result.url.clone(), result)).collect();
+    let mut diff = ScanDiff::default();
+    let new: BTreeMap<String, ScanResult> =
+        new.into_iter().map(|result| (result.url.clone(), result)).collect();
+    for (url, new_result) in new {
+        match old.remove(&url) {
+            None => diff.added.push(new_result),
+            Some(old_result)
+                if old_result.status != new_result.status
+                    || old_result
+                        .chars
+                        .zip(new_result.chars)
+                        .is_some_and(|(old, new)| old != new) =>
+            {
+                diff.changed.push(ChangedResult {
+                    old: old_result,
+                    new: new_result,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = old.into_values().collect();
+    diff
+}
+
+impl ScanDiff {
+    /// Renders the diff as `+`/`-`/`~` lines followed by a summary comment.
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        for result in &self.added {
+            out.push_str(&format!("+ {}\n", result.line));
+        }
+        for result in &self.removed {
+            out.push_str(&format!("- {}\n", result.line));
+        }
+        for ChangedResult { old, new } in &self.changed {
+            out.push_str(&format!(
+                "~ {} (status {} -> {}, {}C -> {}C)\n",
+                new.url,
+                old.status,
+                new.status,
+                dirnutek::format_count(old.chars),
+                dirnutek::format_count(new.chars)
+            ));
+        }
+        out.push_str(&format!(
+            "# {} added, {} removed, {} changed.\n",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        ));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff_results;
+    use crate::output::ScanResult;
+
+    fn results(lines: &[&str]) -> Vec<ScanResult> {
+        lines.iter().map(|line| ScanResult::parse(line.to_string())).collect()
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let old = results(&[
+            "[200 OK] http://example.com/admin [1W, 10C, 1L]",
+            "[200 OK] http://example.com/backup [1W, 10C, 1L]",
+            "[301 Moved Permanently] http://example.com/docs -> /docs/ [0W, 0C, 0L]",
+        ]);
+        let new = results(&[
+            "[403 Forbidden] http://example.com/admin [1W, 9C, 1L]",
+            "[301 Moved Permanently] http://example.com/docs -> /docs/ [0W, 0C, 0L]",
+            "[200 OK] http://example.com/login [2W, 20C, 1L]",
+        ]);
+
+        let diff = diff_results(old, new);
+        assert_eq!(
+            diff.render_text(),
+            "+ [200 OK] http://example.com/login [2W, 20C, 1L]\n\
+             - [200 OK] http://example.com/backup [1W, 10C, 1L]\n\
+             ~ http://example.com/admin (status 200 -> 403, 10C -> 9C)\n\
+             # 1 added, 1 removed, 1 changed.\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_results_skips_unknown_sizes() {
+        let old = results(&[
+            "[200 OK] http://example.com/admin [1W, 10C, 1L]",
+            "[200 OK] http://example.com/login [1W, 10C, 1L]",
+        ]);
+        let new = results(&[
+            "[200 OK] http://example.com/admin [?W, ?C, ?L]",
+            "[403 Forbidden] http://example.com/login [?W, ?C, ?L]",
+        ]);
+
+        let diff = diff_results(old, new);
+        assert_eq!(
+            diff.render_text(),
+            "~ http://example.com/login (status 200 -> 403, 10C -> ?C)\n\
+             # 0 added, 0 removed, 1 changed.\n"
+        );
+    }
+}
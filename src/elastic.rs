@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use dirnutek::{Finding, ScanEvent};
+use reqwest::StatusCode;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// A bulk request is sent once this many findings are waiting.
+const ES_BATCH_SIZE: usize = 500;
+
+/// Waiting findings are sent at least this often, so they show up during long scans.
+const ES_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// At most this many findings wait for the indexer. When Elasticsearch falls behind,
+/// further findings are not indexed rather than slowing down the scan.
+const ES_QUEUE_SIZE: usize = 10_000;
+
+/// How often a bulk request rejected with `429 Too Many Requests` or `503 Service
+/// Unavailable` is sent, waiting twice as long before each retry.
+const ES_MAX_ATTEMPTS: u32 = 4;
+
+/// Indexes every found URL into Elasticsearch with the `_bulk` API (`--es-url`).
+pub struct EsSink {
+    client: reqwest::Client,
+    bulk_url: String,
+    index: String,
+    /// Whether a warning has been printed; later failures are only counted.
+    warned: bool,
+    failed: usize,
+}
+
+impl EsSink {
+    pub fn new(client: reqwest::Client, es_url: &str, index: &str) -> Self {
+        EsSink {
+            client,
+            bulk_url: format!("{}/_bulk", es_url.trim_end_matches('/')),
+            index: index.to_string(),
+            warned: false,
+            failed: 0,
+        }
+    }
+
+    /// Queues every found URL for indexing and forwards all events to the returned
+    /// receiver, so the console or TUI consumer works unchanged. The returned task
+    /// finishes once the last batch has been sent after the scan.
+    pub fn tee(self, mut rx: mpsc::Receiver<ScanEvent>) -> (mpsc::Receiver<ScanEvent>, JoinHandle<()>) {
+        let (tx, tee_rx) = mpsc::channel(100);
+        let (queue_tx, queue_rx) = mpsc::channel(ES_QUEUE_SIZE);
+        let indexer = tokio::spawn(self.run(queue_rx));
+        tokio::spawn(async move {
+            let mut dropped = 0;
+            while let Some(event) = rx.recv().await {
+                if let ScanEvent::FoundUrl(finding) = &event
+                    && queue_tx.try_send(finding.clone()).is_err()
+                {
+                    if dropped == 0 {
+                        eprintln!(
+                            "Warning: Elasticsearch is falling behind; some hits will not be indexed."
+                        );
+                    }
+                    dropped += 1;
+                }
+                if tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+            if dropped > 0 {
+                eprintln!("Warning: {} hits were not indexed into Elasticsearch.", dropped);
+            }
+        });
+        (tee_rx, indexer)
+    }
+
+    async fn run(mut self, mut queue: mpsc::Receiver<Finding>) {
+        let mut batch = Vec::new();
+        let mut interval = tokio::time::interval(ES_FLUSH_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                finding = queue.recv() => match finding {
+                    Some(finding) => {
+                        batch.push(finding);
+                        if batch.len() >= ES_BATCH_SIZE {
+                            self.flush(&mut batch).await;
+                        }
+                    }
+                    None => break,
+                },
+                _ = interval.tick() => self.flush(&mut batch).await,
+            }
+        }
+        self.flush(&mut batch).await;
+        if self.failed > 0 {
+            eprintln!("Warning: {} hits could not be indexed into Elasticsearch.", self.failed);
+        }
+    }
+
+    /// Sends the waiting findings in one bulk request and empties `batch`.
+    async fn flush(&mut self, batch: &mut Vec<Finding>) {
+        if batch.is_empty() {
+            return;
+        }
+        let action = serde_json::json!({ "index": { "_index": self.index } }).to_string();
+        let mut body = String::new();
+        for finding in batch.iter() {
+            let Ok(document) = serde_json::to_string(finding) else {
+                continue;
+            };
+            body.push_str(&action);
+            body.push('\n');
+            body.push_str(&document);
+            body.push('\n');
+        }
+        let count = batch.len();
+        batch.clear();
+        match self.send(body).await {
+            Ok(errors) if errors.is_empty() => {}
+            Ok(errors) => self.fail(errors.len(), errors[0].clone()),
+            Err(e) => self.fail(count, e),
+        }
+    }
+
+    /// Sends a bulk request, retrying while Elasticsearch is overloaded, and returns the
+    /// errors of the rejected documents.
+    async fn send(&self, body: String) -> Result<Vec<String>, String> {
+        let mut backoff = Duration::from_millis(500);
+        for attempt in 1..=ES_MAX_ATTEMPTS {
+            let response = self
+                .client
+                .post(&self.bulk_url)
+                .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+                .body(body.clone())
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            let status = response.status();
+            if matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE)
+                && attempt < ES_MAX_ATTEMPTS
+            {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(format!("{} from {}", status, self.bulk_url));
+            }
+            let result: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+            if result["errors"].as_bool() != Some(true) {
+                return Ok(Vec::new());
+            }
+            let errors = result["items"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|item| &item["index"]["error"])
+                .filter(|error| !error.is_null())
+                .map(|error| error["reason"].as_str().map_or_else(|| error.to_string(), String::from))
+                .collect();
+            return Ok(errors);
+        }
+        unreachable!("the last attempt returns")
+    }
+
+    fn fail(&mut self, count: usize, reason: String) {
+        if !self.warned {
+            eprintln!("Warning: Could not index hits into Elasticsearch: {}", reason);
+            self.warned = true;
+        }
+        self.failed += count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EsSink;
+    use dirnutek::{Finding, ScanEvent};
+    use httptest::{Expectation, Server, matchers::*, responders::*};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_es_tee_bulk_indexes_found_urls_at_scan_end() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of(vec![
+                Box::new(request::method_path("POST", "/_bulk")),
+                Box::new(request::headers(contains(("content-type", "application/x-ndjson")))),
+                Box::new(request::body(matches(r#""_index":"scans""#))),
+                Box::new(request::body(matches(r#""url":"http://example.com/admin""#))),
+                Box::new(request::body(matches(r#""url":"http://example.com/login""#))),
+            ]))
+            .times(1)
+            .respond_with(status_code(200).body(r#"{"errors":false,"items":[]}"#)),
+        );
+
+        let sink = EsSink::new(reqwest::Client::new(), &server.url_str("/"), "scans");
+        let (tx, rx) = mpsc::channel(10);
+        let (mut rx, indexer) = sink.tee(rx);
+        for path in ["admin", "login"] {
+            let finding = Finding {
+                url: format!("http://example.com/{}", path),
+                status: 200,
+                ..Default::default()
+            };
+            tx.send(ScanEvent::FoundUrl(finding)).await.unwrap();
+        }
+        tx.send(ScanEvent::ScanFinished).await.unwrap();
+        drop(tx);
+
+        assert!(matches!(rx.recv().await, Some(ScanEvent::FoundUrl(_))));
+        assert!(matches!(rx.recv().await, Some(ScanEvent::FoundUrl(_))));
+        assert_eq!(rx.recv().await, Some(ScanEvent::ScanFinished));
+        assert_eq!(rx.recv().await, None);
+        indexer.await.unwrap();
+    }
+}
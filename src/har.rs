@@ -0,0 +1,298 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+/// Response bodies longer than this many bytes are truncated in the archive.
+pub const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Collects the requests and responses of a scan and writes them as a HAR 1.2 archive.
+#[derive(Debug, Default)]
+pub struct HarLog {
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl HarLog {
+    pub async fn push(&self, entry: Entry) {
+        self.entries.lock().await.push(entry);
+    }
+
+    pub async fn len(&self) -> usize {
+        self.entries.lock().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.entries.lock().await.is_empty()
+    }
+
+    /// Serializes the archive, with the entries ordered by start time.
+    pub async fn to_json(&self) -> serde_json::Result<String> {
+        let mut entries = self.entries.lock().await.clone();
+        entries.sort_by(|a, b| a.started_date_time.cmp(&b.started_date_time));
+        let archive = Archive {
+            log: Log {
+                version: "1.2",
+                creator: Creator {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                entries,
+            },
+        };
+        serde_json::to_string_pretty(&archive)
+    }
+
+    pub async fn write(&self, path: &Path) -> anyhow::Result<()> {
+        tokio::fs::write(path, self.to_json().await?).await?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct Archive {
+    log: Log,
+}
+
+#[derive(Serialize)]
+struct Log {
+    version: &'static str,
+    creator: Creator,
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+/// One request/response pair of the archive.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    started_date_time: String,
+    time: f64,
+    request: Request,
+    response: Response,
+    cache: Cache,
+    timings: Timings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: i64,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Cache {}
+
+#[derive(Debug, Clone, Serialize)]
+struct Timings {
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+impl Entry {
+    /// Starts an entry for a request that is about to be sent. Only the headers set by
+    /// dirnutek are known at this point; headers the HTTP client adds itself (such as
+    /// `User-Agent`) are not listed.
+    pub fn new(request: &reqwest::Request) -> Self {
+        let content_type = header_value(request.headers(), reqwest::header::CONTENT_TYPE);
+        let body = request.body().and_then(|body| body.as_bytes());
+        Self {
+            started_date_time: format_timestamp(SystemTime::now()),
+            time: 0.0,
+            request: Request {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                http_version: format!("{:?}", request.version()),
+                cookies: Vec::new(),
+                headers: name_values(request.headers()),
+                query_string: request
+                    .url()
+                    .query_pairs()
+                    .map(|(name, value)| NameValue {
+                        name: name.into_owned(),
+                        value: value.into_owned(),
+                    })
+                    .collect(),
+                post_data: body.map(|body| PostData {
+                    mime_type: content_type,
+                    text: String::from_utf8_lossy(body).into_owned(),
+                }),
+                headers_size: -1,
+                body_size: body.map_or(0, |body| body.len() as i64),
+            },
+            response: Response {
+                status: 0,
+                status_text: String::new(),
+                http_version: String::new(),
+                cookies: Vec::new(),
+                headers: Vec::new(),
+                content: Content::default(),
+                redirect_url: String::new(),
+                headers_size: -1,
+                body_size: -1,
+            },
+            cache: Cache {},
+            timings: Timings {
+                send: 0.0,
+                wait: 0.0,
+                receive: 0.0,
+            },
+        }
+    }
+
+    /// Records the status and headers of the response, received `wait` after sending.
+    pub fn set_response(&mut self, response: &reqwest::Response, wait: Duration) {
+        let status = response.status();
+        self.request.http_version = format!("{:?}", response.version());
+        self.response.status = status.as_u16();
+        self.response.status_text = status.canonical_reason().unwrap_or_default().to_string();
+        self.response.http_version = format!("{:?}", response.version());
+        self.response.headers = name_values(response.headers());
+        self.response.content.mime_type =
+            header_value(response.headers(), reqwest::header::CONTENT_TYPE);
+        self.response.redirect_url = header_value(response.headers(), reqwest::header::LOCATION);
+        self.timings.wait = millis(wait);
+        self.time = self.timings.wait;
+    }
+
+    /// Records the response body, read in `receive`. Bodies longer than
+    /// [`MAX_BODY_SIZE`] bytes are truncated.
+    pub fn set_body(&mut self, body: &str, receive: Duration) {
+        let content = &mut self.response.content;
+        content.size = body.len() as i64;
+        if body.len() > MAX_BODY_SIZE {
+            let mut end = MAX_BODY_SIZE;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            content.text = Some(body[..end].to_string());
+            content.comment = Some(format!("Truncated to {} bytes.", end));
+        } else {
+            content.text = Some(body.to_string());
+        }
+        self.response.body_size = body.len() as i64;
+        self.timings.receive = millis(receive);
+        self.time = self.timings.wait + self.timings.receive;
+    }
+}
+
+fn name_values(headers: &reqwest::header::HeaderMap) -> Vec<NameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| NameValue {
+            name: name.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        })
+        .collect()
+}
+
+fn header_value(headers: &reqwest::header::HeaderMap, name: reqwest::header::HeaderName) -> String {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Formats a time as an ISO 8601 (RFC 3339) UTC timestamp with milliseconds, as
+/// required by HAR.
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_timestamp;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_millis(1_709_251_199_123)),
+            "2024-02-29T23:59:59.123Z"
+        );
+    }
+}
@@ -1,17 +1,46 @@
 use anyhow::Result;
+use async_compression::tokio::bufread::GzipDecoder;
 use clap::ValueEnum;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader, Lines};
 use tokio::sync::{Mutex, Semaphore, mpsc::Sender, broadcast}; // Add broadcast
-use tokio::task::JoinSet;
+use tokio::task::{JoinError, JoinSet};
 
+pub mod bloom;
+pub mod charset;
+pub mod cookies;
+pub mod har;
+pub mod rate;
+pub mod raw;
+
+use bloom::BloomFilter;
+
+// Findings are the bulk of the events anyway, so boxing them would not save memory.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ScanEvent {
     /// A new URL has been found.
-    FoundUrl(String),
-    /// A request has been completed.
-    RequestCompleted,
+    FoundUrl(Finding),
+    /// A request has been completed; its response arrived `elapsed` after it was sent.
+    RequestCompleted { elapsed: Duration },
     /// An error occurred during a request.
     ErrorOccurred(String),
     /// The scan has started, providing the total number of words in the wordlist.
@@ -22,6 +51,153 @@ pub enum ScanEvent {
     ScanStopped,
     /// A warning message.
     Warning(String),
+    /// The failed words have been replayed; `recovered` of `replayed` succeeded.
+    ReplayFinished { replayed: usize, recovered: usize },
+    /// A burst of requests has been sent; the next one starts after `pause`.
+    BurstPaused { pause: Duration },
+    /// The pause between bursts is over and requests are sent again.
+    BurstResumed,
+    /// The directories up to `depth - 1` have been scanned; the directories at `depth`
+    /// are scanned after `pause` (`level_delay`).
+    LevelPaused { depth: usize, pause: Duration },
+    /// With `recursion_preview`, the directories the base URL scan would recurse into,
+    /// and about how many `requests` scanning them would take.
+    RecursionPreview { directories: Vec<String>, requests: usize },
+}
+
+/// A reported response. Its `Display` output is the plain result line, e.g.
+/// `[200 OK] http://example.com/admin [1W, 5C, 1L]`, with `?` for unknown counts. The
+/// alternate form (`{:#}`) adds the time to first byte:
+/// `[200 OK] http://example.com/admin [1W, 5C, 1L, 12ms]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Finding {
+    pub url: String,
+    pub status: u16,
+    /// The `Location` of a redirect response, resolved against the request URL, or the
+    /// target of a client-side redirect.
+    pub redirect_target: Option<String>,
+    /// The number of words, characters and lines of the body. Unknown (`None`) if the
+    /// body was not read: for a HEAD request, e.g. with `head_first`, or once
+    /// `max_total_bytes` is used up.
+    pub words: Option<usize>,
+    pub chars: Option<usize>,
+    pub lines: Option<usize>,
+    /// The HTTP method the request was sent with.
+    pub method: String,
+    /// When the response was received, as an RFC 3339 UTC timestamp.
+    pub timestamp: String,
+    pub classification: Classification,
+    /// With `follow_redirects`, each URL from `url` to the final one with its status.
+    /// Empty if the response was no redirect.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redirect_chain: Vec<RedirectHop>,
+    /// The number of redirects followed to reach `status`.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub redirects: usize,
+    /// Milliseconds from sending the request of the reported response (the last retry,
+    /// or the last redirect hop) until its status line and headers arrived: the time
+    /// to first byte.
+    pub ttfb_ms: u64,
+    /// Milliseconds from sending the request until the body was read. Includes
+    /// `ttfb_ms`; the difference is the transfer time of the body.
+    pub total_ms: u64,
+    /// Whether `redirect_target` comes from a `<meta http-equiv="refresh">` tag or a
+    /// script rather than from the status code.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub client_redirect: bool,
+    /// The header and value sent with `request_id_header`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<RequestId>,
+    /// The requested word, with `show_word`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub word: Option<String>,
+}
+
+/// Renders a count of a [`Finding`], `?` if it is unknown.
+pub fn format_count(count: Option<usize>) -> String {
+    count.map_or_else(|| "?".to_string(), |count| count.to_string())
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// What the status code of a finding says about the path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Classification {
+    /// 2xx: the content was served.
+    #[default]
+    Found,
+    /// 3xx: the path exists and points elsewhere.
+    Redirect,
+    /// 401, 403 or 407: the path exists but is protected, which is often worth a
+    /// closer look.
+    Protected,
+    /// Any other status code, e.g. server errors.
+    Other,
+}
+
+impl Classification {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            200..=299 => Classification::Found,
+            300..=399 => Classification::Redirect,
+            401 | 403 | 407 => Classification::Protected,
+            _ => Classification::Other,
+        }
+    }
+}
+
+/// One response of a followed redirect chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedirectHop {
+    pub url: String,
+    pub status: u16,
+}
+
+/// The unique ID sent in the `request_id_header` of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestId {
+    pub header: String,
+    pub id: u64,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match reqwest::StatusCode::from_u16(self.status) {
+            Ok(status) => write!(f, "[{}] {}", status, self.url)?,
+            Err(_) => write!(f, "[{}] {}", self.status, self.url)?,
+        }
+        for hop in self.redirect_chain.iter().skip(1) {
+            write!(f, " -> {}", hop.url)?;
+        }
+        if let Some(target) = &self.redirect_target {
+            write!(f, " -> {}", target)?;
+            if self.client_redirect {
+                f.write_str(" (client redirect)")?;
+            }
+        }
+        write!(
+            f,
+            " [{}W, {}C, {}L",
+            format_count(self.words),
+            format_count(self.chars),
+            format_count(self.lines)
+        )?;
+        if f.alternate() {
+            write!(f, ", {}ms", self.ttfb_ms)?;
+        }
+        f.write_str("]")?;
+        if let Some(RequestId { header, id }) = &self.request_id {
+            write!(f, " [{}: {}]", header, id)?;
+        }
+        if let Some(word) = &self.word {
+            write!(f, " [word: {}]", word)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -30,9 +206,10 @@ pub enum ControlEvent {
     Stop,
 }
 
-#[derive(Debug, Clone, ValueEnum, PartialEq)]
+#[derive(Debug, Clone, ValueEnum, PartialEq, Default)]
 pub enum FuzzMode {
     /// Fuzzes the path segment of the URL (default).
+    #[default]
     Path,
     /// Fuzzes a subdomain, indicated by FUZZ.example.com.
     Subdomain,
@@ -40,8 +217,579 @@ pub enum FuzzMode {
     Parameter,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+/// Everything that configures a scan, passed to [`start_scan`] and [`perform_scan`] as
+/// a single value. Start from `ScanConfig::default()` (a GET scan of the paths, without
+/// recursion, reporting every status but 404) and set the fields that differ.
+#[derive(Debug, Clone, Default)]
+pub struct ScanConfig {
+    /// Maximum number of requests in flight. 0 is treated as 1.
+    pub concurrency: usize,
+    pub http_method: HttpMethod,
+    /// Only report these statuses. Takes precedence over `exclude_status`.
+    pub include_status: Option<HashSet<u16>>,
+    /// Do not report these statuses. Without either list, all but 404 are reported.
+    pub exclude_status: Option<HashSet<u16>>,
+    /// Number of directory levels to scan: 0 and 1 scan only the base URL, 2 also the
+    /// directories found there, and so on.
+    pub max_depth: usize,
+    /// Milliseconds to wait before each request.
+    pub delay: Option<u64>,
+    /// Only report responses with one of these word counts.
+    pub exact_words: Option<Vec<usize>>,
+    /// Only report responses with one of these char counts.
+    pub exact_chars: Option<Vec<usize>>,
+    /// Only report responses with one of these line counts.
+    pub exact_lines: Option<Vec<usize>>,
+    /// Do not report responses with one of these word counts.
+    pub exclude_exact_words: Option<Vec<usize>>,
+    /// Do not report responses with one of these char counts.
+    pub exclude_exact_chars: Option<Vec<usize>>,
+    /// Do not report responses with one of these line counts.
+    pub exclude_exact_lines: Option<Vec<usize>>,
+    pub fuzz_mode: FuzzMode,
+    /// Headers sent with every request, as `Name: value`; the keyword is replaced in
+    /// the values.
+    pub headers: Vec<String>,
+    /// The body of POST requests; the keyword is replaced in it.
+    pub data: Option<String>,
+    /// Directories already queued for recursion, so each is scanned once.
+    pub visited_urls: Arc<Mutex<HashSet<url::Url>>>,
+    /// Maximum number of response body bytes to read. Longer bodies are truncated
+    /// before the word/char/line counts are computed.
+    pub max_response_size: Option<usize>,
+    /// Abort a request whose body has not been read completely this long after the
+    /// headers arrived, so servers that trickle the body cannot hold a task until the
+    /// request timeout.
+    pub body_timeout: Option<Duration>,
+    /// Do not download the body of responses with these status codes, like for
+    /// redirects; their word/char/line counts are reported as 0.
+    pub no_body_for: HashSet<u16>,
+    /// Only recurse into found URLs with these status codes. When `None`, every
+    /// successful or redirect response is recursed into.
+    pub recurse_status: Option<HashSet<u16>>,
+    /// Do not recurse into found URLs whose path contains one of these substrings,
+    /// e.g. `/static/`. They are still reported.
+    pub no_recurse_paths: Vec<String>,
+    /// Also recurse into found URLs on other hosts than the scanned one. By default
+    /// they are still reported, but not scanned.
+    pub allow_offsite_recursion: bool,
+    /// Warn about every response that arrives later than this after its request was
+    /// sent.
+    pub slow_threshold: Option<Duration>,
+    /// Before a POST with a body, probe the fuzzed URL with a GET, and only send the
+    /// body if the probe response matches this regex, e.g. `<form`. Without it, POST
+    /// bodies are sent to the unfuzzed base URL.
+    pub conditional_body: Option<Regex>,
+    /// Stream words from this file for every scanned directory instead of using
+    /// the in-memory word list.
+    pub wordlist_path: Option<PathBuf>,
+    /// Generate the words from this charset instead of using the word list or
+    /// `wordlist_path`. The words are generated as they are scanned.
+    pub charset: Option<charset::Charset>,
+    /// Further wordlists for numbered keywords: `FUZZ2` is replaced by a word of the
+    /// first of these lists, `FUZZ3` by one of the second and so on, while `FUZZ1` and
+    /// a plain `FUZZ` take the main word. Every combination of words is requested,
+    /// unless `pitchfork` is set.
+    pub extra_wordlists: Vec<Arc<Vec<String>>>,
+    /// Combine the main word with the words at the same position of `extra_wordlists`
+    /// instead of requesting every combination. The scan stops at the end of the
+    /// shortest list.
+    pub pitchfork: bool,
+    /// Remember visited URLs in a fixed-size bloom filter instead of `visited_urls`.
+    /// False positives may occasionally skip recursing into a directory.
+    pub low_memory: bool,
+    /// Re-run words that failed with a retryable error (see `retry_classifier`) once,
+    /// after the main pass.
+    pub replay_failed: bool,
+    /// Pause in milliseconds before the failed words are replayed.
+    pub replay_backoff: u64,
+    /// Before scanning, request a bogus word to learn the target's default response
+    /// (e.g. the catch-all vhost) and only report responses that differ from it.
+    pub vhost_baseline: bool,
+    /// Responses with this signature are not reported. Filled in by [`start_scan`]
+    /// when `vhost_baseline` is set.
+    pub baseline: Option<ResponseSignature>,
+    /// Before scanning, request this known-bad URL (resolved against the scanned URL, so
+    /// a path like `/dirnutek-missing` works for every target) and use its response as
+    /// the `baseline`.
+    pub baseline_url: Option<String>,
+    /// Responses with the baseline status whose size differs from the baseline by at
+    /// most this many characters are not reported either, for soft-404 pages that
+    /// vary slightly (e.g. because they echo the requested path).
+    pub baseline_threshold: usize,
+    /// Before scanning, request [`AUTO_FILTER_PROBES`] random words to learn the
+    /// target's soft-404 responses, and hide responses that look the same.
+    pub auto_filter: bool,
+    /// The soft-404 responses learned for `auto_filter`. Filled in by [`start_scan`].
+    pub calibrated: Vec<CalibratedSignature>,
+    /// Halve the concurrency (down to 1) every time the server closes connections
+    /// with [`GOAWAY_THROTTLE_THRESHOLD`] HTTP/2 GOAWAY frames.
+    pub adaptive_throttle: bool,
+    /// Only report the first response with a given status and body; later responses
+    /// with an identical body hash (aliases, symlinks) are reported as warnings instead.
+    /// Responses without a body (HEAD requests, redirects, `no_body_for`) are always
+    /// reported.
+    pub dedupe_by_body_hash: bool,
+    /// SHA-1 digests of the statuses and bodies seen so far. Shared by all clones of
+    /// these options, so deduplication spans recursion levels and targets.
+    pub body_hashes: Arc<Mutex<HashSet<[u8; 20]>>>,
+    /// Apply the user headers in a random order for every request. Header name casing
+    /// cannot be varied: HTTP/2 requires lowercase names and reqwest normalizes them for
+    /// HTTP/1.1 as well.
+    pub header_jitter: bool,
+    /// Send every request with a User-Agent picked from [`USER_AGENTS`] instead of the
+    /// one of the client. A User-Agent header given in `headers` wins.
+    pub random_agent: bool,
+    /// The source of every random choice of the scan: User-Agents, header order,
+    /// auto-filter probes and level delays. Shared by all clones of these options.
+    pub rng: ScanRng,
+    /// Send this header with a unique, increasing ID on every request and append the
+    /// ID to the reported result, so results can be matched with server logs.
+    pub request_id_header: Option<String>,
+    /// The last request ID handed out. Shared by all clones of these options.
+    pub request_ids: Arc<AtomicU64>,
+    /// Inspect the headers of a preflight request for known WAFs and CDNs and warn
+    /// about the detected product.
+    pub detect_waf: bool,
+    /// When a WAF or CDN is detected, lower the concurrency to [`WAF_MAX_CONCURRENCY`]
+    /// and delay requests by at least [`WAF_MIN_DELAY_MS`]. Requires `detect_waf`.
+    pub adapt_to_waf: bool,
+    /// Skip this many words at the start of the wordlist, e.g. to resume an interrupted
+    /// scan. Applies to every scanned directory.
+    pub skip_words: usize,
+    /// Send these fields as an `application/x-www-form-urlencoded` body of POST
+    /// requests instead of the raw data, with the keyword replaced in every value.
+    pub form_data: Option<Vec<(String, String)>>,
+    /// Compress the raw POST body after the keyword is replaced, and send it with the
+    /// matching `Content-Encoding` header.
+    pub compress_body: Option<BodyCompression>,
+    /// The placeholder replaced by each word. Defaults to [`DEFAULT_FUZZ_KEYWORD`].
+    pub fuzz_keyword: Option<String>,
+    /// Do not recurse into found URLs longer than this many characters, as a guard
+    /// against sites that keep appending path segments.
+    pub max_url_length: Option<usize>,
+    /// Aggressive: also request null-byte and trailing-character variants of words
+    /// with a file extension (`index.php%00.jpg`, `index.php;.jpg`, `index.php.`,
+    /// `index.php%20`) to find misconfigured file handlers. Path fuzzing only.
+    pub extension_bypass: bool,
+    /// Also request every word with each of these extensions appended, e.g. `php` for
+    /// `admin.php`. Given without the leading dot. Path fuzzing only.
+    pub extensions: Vec<String>,
+    /// For HTML hits, detect client-side redirects (`<meta http-equiv="refresh">`,
+    /// `window.location = ...`), report their target and request targets on the same
+    /// origin once. Path fuzzing only.
+    pub follow_meta_refresh: bool,
+    /// Client-side redirect targets found by [`perform_scan`] that [`start_scan`] has
+    /// not queued yet. Shared by all clones of these options.
+    pub client_redirects: Arc<Mutex<Vec<url::Url>>>,
+    /// Retry a request this many times when it fails with a network error or a 5xx
+    /// response, before reporting it. 4xx responses are never retried.
+    pub retries: usize,
+    /// Pause in milliseconds before the first retry of a request; doubled for every
+    /// further retry.
+    pub retry_backoff: u64,
+    /// Decides which network errors are retried (`retries`) and replayed
+    /// (`replay_failed`). Defaults to [`is_retryable_error`].
+    pub retry_classifier: RetryClassifier,
+    /// Record the requests and responses of the scan in this HAR archive. Requests that
+    /// fail without a response are not recorded.
+    pub har: Option<Arc<har::HarLog>>,
+    /// Only record the reported hits in `har`.
+    pub har_hits_only: bool,
+    /// Send the cookies set by earlier responses (`Set-Cookie`) with each request.
+    /// Shared by all clones of these options, so a rotated cookie reaches the next
+    /// request whichever task sends it.
+    pub cookie_jar: Option<Arc<cookies::CookieJar>>,
+    /// Cookies (`name=value`) sent with every request in one `Cookie` header, with the
+    /// keyword replaced like in headers. They override cookies of `cookie_jar` with the
+    /// same name.
+    pub cookies: Vec<String>,
+    /// Send the requests with this body framing over a hand-written HTTP/1.1
+    /// connection instead of with the client. For protocol testing only.
+    pub raw_framing: Option<raw::RawFraming>,
+    /// Limit all requests of the scan, retries and followed redirects included, to this
+    /// shared rate. Shared by all clones of these options.
+    pub rate_limiter: Option<Arc<rate::RateLimiter>>,
+    /// Only report responses whose body matches this regex.
+    pub match_regex: Option<Regex>,
+    /// Do not report responses whose body matches this regex.
+    pub filter_regex: Option<Regex>,
+    /// Only report responses that have a header with each of these names, whatever
+    /// its value.
+    pub match_has_headers: Vec<String>,
+    /// Do not report responses that have a header with one of these names.
+    pub filter_has_headers: Vec<String>,
+    /// Only report responses whose media type starts with one of these, e.g.
+    /// `text/html` or `application/`. The charset and other parameters are ignored.
+    pub match_content_types: Vec<String>,
+    /// Do not report responses whose media type starts with one of these.
+    pub filter_content_types: Vec<String>,
+    /// Only report responses whose word, char and line counts are within these
+    /// ranges. Checked after the exact count filters; a response must pass both.
+    pub word_range: CountRange,
+    pub char_range: CountRange,
+    pub line_range: CountRange,
+    /// Only report responses whose time to first byte in milliseconds is within this
+    /// range. Applies in every `match_mode`.
+    pub ttfb_range: CountRange,
+    /// Whether all or any of the match conditions must pass.
+    pub match_mode: MatchMode,
+    /// HTTP Basic authentication (user name and password) for every request. An
+    /// `Authorization` header among the user headers takes precedence.
+    pub basic_auth: Option<(String, String)>,
+    /// Follow at most this many redirects per request and report the final response
+    /// with the chain that led to it.
+    pub follow_redirects: Option<usize>,
+    /// When no request has completed for this many seconds while requests are in
+    /// flight, abort those requests and carry on, so a few hanging connections cannot
+    /// stall the scan.
+    pub idle_timeout: Option<u64>,
+    /// Send requests in bursts separated by pauses, to stay below rate-based detection.
+    pub burst: Option<Burst>,
+    /// Pause for a random number of milliseconds in this range (inclusive) after a
+    /// recursion level has been scanned and before the next, deeper one starts, so the
+    /// traffic has gaps. Requests of the previous level are completed first.
+    pub level_delay: Option<(u64, u64)>,
+    /// Words for the directories found by recursion (depth > 0), e.g. a smaller,
+    /// targeted list. The main word list (and `skip_words`) only seeds the base URL.
+    pub recursion_words: Option<Arc<Vec<String>>>,
+    /// Append the requested word to each reported result (`[word: ...]`), since the
+    /// URL may not show it verbatim (encoding, extension bypass variants).
+    pub show_word: bool,
+    /// Bandwidth budget for response bodies. Once `downloaded_bytes` exceeds it, GET
+    /// requests are sent as HEAD and other bodies are no longer read, so size-based
+    /// filters see empty bodies from then on.
+    pub max_total_bytes: Option<u64>,
+    /// Response body bytes read so far. Shared by all clones of these options.
+    pub downloaded_bytes: Arc<AtomicU64>,
+    /// Scan the most recently found directory next (LIFO) instead of the one found
+    /// first (FIFO), to reach deep content quickly.
+    pub depth_first: bool,
+    /// Scan only the base URL and report the directories that would be recursed into,
+    /// regardless of `max_depth`, as [`ScanEvent::RecursionPreview`] instead of
+    /// scanning them.
+    pub recursion_preview: bool,
+    /// Send GET requests as HEAD when no option needs the response body, so bodies are
+    /// never downloaded and the counts of hits are unknown (`None`, printed as `?`).
+    pub head_first: bool,
+}
+
+/// The former name of [`ScanConfig`].
+#[deprecated(note = "renamed to ScanConfig")]
+pub type ScanOptions = ScanConfig;
+
+/// An inclusive range of word, char or line counts; either bound may be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CountRange {
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+}
+
+impl CountRange {
+    pub fn contains(&self, count: usize) -> bool {
+        self.min.is_none_or(|min| count >= min) && self.max.is_none_or(|max| count <= max)
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+}
+
+impl std::fmt::Display for CountRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => write!(f, "{}-{}", min, max),
+            (Some(min), None) => write!(f, ">= {}", min),
+            (None, Some(max)) => write!(f, "<= {}", max),
+            (None, None) => f.write_str("any"),
+        }
+    }
+}
+
+/// A burst pattern: send `requests` requests, then pause for `pause`, repeatedly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Burst {
+    pub requests: usize,
+    pub pause: Duration,
+}
+
+/// Decides whether a request that failed with an error should be retried.
+///
+/// The closure is shared by all scan tasks, so it must be `Send + Sync`; use atomics
+/// or a mutex for any state it keeps.
+///
+/// ```
+/// use dirnutek::{RetryClassifier, ScanConfig};
+///
+/// let options = ScanConfig {
+///     replay_failed: true,
+///     // Only retry timeouts.
+///     retry_classifier: RetryClassifier::new(|e| e.is_timeout()),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct RetryClassifier(Arc<dyn Fn(&reqwest::Error) -> bool + Send + Sync>);
+
+impl RetryClassifier {
+    pub fn new(classify: impl Fn(&reqwest::Error) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(classify))
+    }
+
+    pub fn is_retryable(&self, error: &reqwest::Error) -> bool {
+        (self.0)(error)
+    }
+}
+
+impl Default for RetryClassifier {
+    fn default() -> Self {
+        Self::new(is_retryable_error)
+    }
+}
+
+impl std::fmt::Debug for RetryClassifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RetryClassifier(..)")
+    }
+}
+
+/// The default retry policy: timeouts, failed connections and requests that failed
+/// in transit (e.g. a dropped connection) are retried, other errors are not.
+pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect() || error.is_request()
+}
+
+/// The placeholder replaced by each word unless another one is configured.
+pub const DEFAULT_FUZZ_KEYWORD: &str = "FUZZ";
+
+impl ScanConfig {
+    /// The placeholder replaced by each word.
+    pub fn fuzz_keyword(&self) -> &str {
+        self.fuzz_keyword.as_deref().unwrap_or(DEFAULT_FUZZ_KEYWORD)
+    }
+
+    /// Whether an option other than the exact count filters inspects response bodies.
+    fn reads_body(&self) -> bool {
+        !self.word_range.is_unbounded()
+            || !self.char_range.is_unbounded()
+            || !self.line_range.is_unbounded()
+            || self.match_regex.is_some()
+            || self.filter_regex.is_some()
+            || self.baseline.is_some()
+            || self.baseline_url.is_some()
+            || self.vhost_baseline
+            || self.auto_filter
+            || !self.calibrated.is_empty()
+            || self.dedupe_by_body_hash
+            || self.follow_meta_refresh
+    }
+
+    /// Whether the body of a response with `status` is not downloaded: redirects and
+    /// the statuses of `no_body_for`.
+    fn skips_body(&self, status: reqwest::StatusCode) -> bool {
+        status.is_redirection() || self.no_body_for.contains(&status.as_u16())
+    }
+
+    async fn record_har(&self, entry: Option<har::Entry>) {
+        if let (Some(har), Some(entry)) = (&self.har, entry) {
+            har.push(entry).await;
+        }
+    }
+}
+
+/// A seeded random number generator shared by the tasks of a scan, so a scan with
+/// the same seed (and a concurrency of 1) makes the same random choices.
+#[derive(Debug, Clone)]
+pub struct ScanRng(Arc<std::sync::Mutex<StdRng>>);
+
+impl ScanRng {
+    pub fn new(seed: u64) -> Self {
+        ScanRng(Arc::new(std::sync::Mutex::new(StdRng::seed_from_u64(seed))))
+    }
+
+    /// Runs `f` with the generator locked.
+    pub fn with<T>(&self, f: impl FnOnce(&mut StdRng) -> T) -> T {
+        f(&mut self.0.lock().expect("RNG lock poisoned"))
+    }
+}
+
+impl Default for ScanRng {
+    /// A generator with a random seed.
+    fn default() -> Self {
+        ScanRng::new(rand::random())
+    }
+}
+
+/// Common browser User-Agent strings picked from by `random_agent`.
+pub const USER_AGENTS: [&str; 8] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36 Edg/124.0.0.0",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1",
+];
+
+/// Concurrency limit applied by `adapt_to_waf` when a WAF or CDN is detected.
+pub const WAF_MAX_CONCURRENCY: usize = 2;
+
+/// Minimum delay in milliseconds between requests applied by `adapt_to_waf`.
+pub const WAF_MIN_DELAY_MS: u64 = 500;
+
+/// Number of GOAWAY errors after which `adaptive_throttle` halves the concurrency.
+pub const GOAWAY_THROTTLE_THRESHOLD: usize = 3;
+
+/// The status code and body size of a response, used to compare against a baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseSignature {
+    pub status: u16,
+    pub chars: usize,
+}
+
+impl ResponseSignature {
+    /// Whether `other` has the same status and a size within `threshold` characters.
+    pub fn matches(&self, other: &ResponseSignature, threshold: usize) -> bool {
+        self.status == other.status && self.chars.abs_diff(other.chars) <= threshold
+    }
+}
+
+/// How the match conditions (`include_status`, the exact counts, the count ranges and
+/// `match_regex`) are combined. The exclusion filters always apply.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// Report a response only if all match conditions pass.
+    #[default]
+    All,
+    /// Report a response if any match condition passes.
+    Any,
+}
+
+/// Number of random words requested to calibrate `auto_filter`.
+pub const AUTO_FILTER_PROBES: usize = 3;
+
+/// A soft-404 response learned by `auto_filter`. A response matches it if it has the
+/// same status and either the same body or the same word, char and line counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalibratedSignature {
+    pub status: u16,
+    pub words: usize,
+    pub chars: usize,
+    pub lines: usize,
+    pub body_hash: u64,
+}
+
+impl CalibratedSignature {
+    fn new(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            words: body.split_whitespace().count(),
+            chars: body.chars().count(),
+            lines: body.lines().count(),
+            body_hash: body_hash(body),
+        }
+    }
+
+    fn matches(&self, other: &CalibratedSignature) -> bool {
+        self.status == other.status
+            && (self.body_hash == other.body_hash
+                || (self.words, self.chars, self.lines) == (other.words, other.chars, other.lines))
+    }
+}
+
+fn body_hash(body: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The encoding of a compressed request body (`compress_body`).
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum BodyCompression {
+    /// A gzip stream (`Content-Encoding: gzip`).
+    Gzip,
+}
+
+/// Compresses a request body for `--compress-body gzip`.
+fn gzip_encode(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// The HTTP protocol version used to talk to the target.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+pub enum HttpVersion {
+    /// Negotiate the version via ALPN (HTTP/2 over TLS when offered, else HTTP/1.1).
+    #[default]
+    Auto,
+    /// Only use HTTP/1.1.
+    #[value(name = "1.1")]
+    Http1,
+    /// Use HTTP/2 with prior knowledge, also over plain http.
+    #[value(name = "2")]
+    Http2,
+}
+
+/// Why a request failed, as reported in [`ScanEvent::ErrorOccurred`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestErrorKind {
+    /// The server closed the HTTP/2 connection with a GOAWAY frame.
+    GoAway,
+    /// The server reset the HTTP/2 stream with a RST_STREAM frame.
+    StreamReset,
+    /// The request timed out.
+    Timeout,
+    /// The connection could not be established.
+    Connect,
+    /// The body was not received within `body_timeout`, e.g. from a server that sends
+    /// it byte by byte.
+    SlowBody,
+    /// Any other error.
+    Other,
+}
+
+impl std::fmt::Display for RequestErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RequestErrorKind::GoAway => "HTTP/2 GOAWAY",
+            RequestErrorKind::StreamReset => "HTTP/2 stream reset",
+            RequestErrorKind::Timeout => "timeout",
+            RequestErrorKind::Connect => "connection error",
+            RequestErrorKind::SlowBody => "slow response body",
+            RequestErrorKind::Other => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Classifies a request error by walking its source chain for an HTTP/2 error.
+pub fn classify_error(error: &reqwest::Error) -> RequestErrorKind {
+    let mut source = std::error::Error::source(error);
+    while let Some(err) = source {
+        if let Some(h2_error) = err.downcast_ref::<h2::Error>() {
+            if h2_error.is_go_away() {
+                return RequestErrorKind::GoAway;
+            }
+            if h2_error.is_reset() {
+                return RequestErrorKind::StreamReset;
+            }
+        }
+        source = err.source();
+    }
+    if error.is_timeout() {
+        RequestErrorKind::Timeout
+    } else if error.is_connect() {
+        RequestErrorKind::Connect
+    } else {
+        RequestErrorKind::Other
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum, Default)]
 pub enum HttpMethod {
+    #[default]
     GET,
     POST,
     PUT,
@@ -51,44 +799,80 @@ pub enum HttpMethod {
     PATCH,
 }
 
-pub async fn perform_scan(
+/// Separates the words of a combined word, one per wordlist (see `extra_wordlists`).
+const PAYLOAD_SEPARATOR: char = '\u{1f}';
+
+/// Replaces `keyword` in `text` with `word`. For a word combined from several
+/// wordlists, a keyword numbered `n` (`FUZZ2`) takes the nth word and an unnumbered
+/// keyword, or one without a matching wordlist, takes the first.
+fn substitute(text: &str, keyword: &str, word: &str) -> String {
+    let payloads: Vec<&str> = word.split(PAYLOAD_SEPARATOR).collect();
+    if payloads.len() == 1 {
+        return text.replace(keyword, word);
+    }
+    let mut fuzzed = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(keyword) {
+        fuzzed.push_str(&rest[..start]);
+        rest = &rest[start + keyword.len()..];
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        match rest[..digits].parse::<usize>() {
+            Ok(n) if (1..=payloads.len()).contains(&n) => {
+                fuzzed.push_str(payloads[n - 1]);
+                rest = &rest[digits..];
+            }
+            _ => fuzzed.push_str(payloads[0]),
+        }
+    }
+    fuzzed.push_str(rest);
+    fuzzed
+}
+
+/// Builds the fuzzed target URL and request for a single word, substituting `keyword`.
+/// In path mode, the first word of a combined word is appended to the path. With
+/// `extra_wordlists`, keywords in the path are replaced instead, in every mode, so a
+/// target like `http://FUZZ1.example.com/FUZZ2` fuzzes the host and the path at once.
+#[allow(clippy::too_many_arguments)]
+fn build_request(
     client: &Client,
     base_url: &url::Url,
     word: &str,
-    tx: Sender<ScanEvent>, // Changed to ScanEvent
     http_method: &HttpMethod,
-    exclude_status: &Option<HashSet<u16>>,
-    include_status: &Option<HashSet<u16>>,
-    _scan_delay: Option<u64>,
-    exact_words: Option<Vec<usize>>,
-    exact_chars: Option<Vec<usize>>,
-    exact_lines: Option<Vec<usize>>,
-    exclude_exact_words: Option<Vec<usize>>,
-    exclude_exact_chars: Option<Vec<usize>>,
-    exclude_exact_lines: Option<Vec<usize>>,
     fuzz_mode: &FuzzMode,
     headers: &[String],
     data: &Option<String>,
-) -> Result<Option<url::Url>> {
+    options: &ScanConfig,
+) -> Result<(url::Url, reqwest::RequestBuilder)> {
+    let keyword = options.fuzz_keyword();
     let mut target_url = base_url.clone();
 
     // If it's a POST request with data, the word is for the body, not the URL path.
-    // So, we skip the URL fuzzing based on fuzz_mode.
-    if !matches!(http_method, HttpMethod::POST) || data.is_none() {
+    // So, we skip the URL fuzzing based on fuzz_mode, unless the body is only sent to
+    // the fuzzed URLs that pass the `conditional_body` probe.
+    let has_body = data.is_some() || options.form_data.is_some();
+    if !matches!(http_method, HttpMethod::POST) || !has_body || options.conditional_body.is_some() {
+        // Only with several wordlists, so a single keyword still means one position.
+        let path_has_keyword =
+            !options.extra_wordlists.is_empty() && base_url.path().contains(keyword);
+        if path_has_keyword {
+            target_url.set_path(&substitute(base_url.path(), keyword, word));
+        }
         match fuzz_mode {
+            FuzzMode::Path if path_has_keyword => {}
             FuzzMode::Path => {
                 let mut url_string = base_url.to_string();
                 if !url_string.ends_with('/') {
                     url_string.push('/');
                 }
-                url_string.push_str(word);
+                url_string.push_str(word.split(PAYLOAD_SEPARATOR).next().unwrap_or(word));
                 target_url = url::Url::parse(&url_string)?;
             }
             FuzzMode::Subdomain => {
                 let base_host = base_url.host_str().ok_or_else(|| {
                     anyhow::anyhow!("Invalid base URL for subdomain fuzzing: no host")
                 })?;
-                let fuzzed_host = base_host.replace("FUZZ", word);
+                // Host names are lowercased when the URL is parsed.
+                let fuzzed_host = substitute(base_host, &keyword.to_ascii_lowercase(), word);
                 target_url.set_host(Some(&fuzzed_host))?;
             }
             FuzzMode::Parameter => {
@@ -96,17 +880,21 @@ pub async fn perform_scan(
                     .query_pairs()
                     .map(|(k, v)| (k.into_owned(), v.into_owned()))
                     .collect();
+                // Every key and value with the keyword is fuzzed, e.g. `?a=FUZZ&b=FUZZ`
+                // or `?FUZZ=1`.
                 let mut found_fuzz = false;
-                for (_key, val) in query_pairs.iter_mut() {
-                    if val.contains("FUZZ") {
-                        *val = val.replace("FUZZ", word);
-                        found_fuzz = true;
-                        break;
+                for (key, val) in query_pairs.iter_mut() {
+                    for part in [key, val] {
+                        if part.contains(keyword) {
+                            *part = substitute(part, keyword, word);
+                            found_fuzz = true;
+                        }
                     }
                 }
                 if !found_fuzz {
                     anyhow::bail!(
-                        "FUZZ keyword not found in query parameters for parameter fuzzing."
+                        "{} keyword not found in query parameters for parameter fuzzing.",
+                        keyword
                     );
                 }
                 target_url
@@ -127,11 +915,20 @@ pub async fn perform_scan(
         HttpMethod::PATCH => client.patch(target_url.as_str()),
     };
 
-    if let HttpMethod::POST = http_method {
-        if let Some(body_data) = data {
-            let fuzzed_body = body_data.replace("FUZZ", word);
-            request_builder = request_builder.body(fuzzed_body);
-        }
+    if let (HttpMethod::POST, Some(form)) = (http_method, &options.form_data) {
+        let fields: Vec<(&str, String)> = form
+            .iter()
+            .map(|(name, value)| (name.as_str(), substitute(value, keyword, word)))
+            .collect();
+        request_builder = request_builder.form(&fields);
+    } else if let (HttpMethod::POST, Some(body_data)) = (http_method, data) {
+        let fuzzed_body = substitute(body_data, keyword, word);
+        request_builder = match options.compress_body {
+            Some(BodyCompression::Gzip) => request_builder
+                .header(reqwest::header::CONTENT_ENCODING, "gzip")
+                .body(gzip_encode(fuzzed_body.as_bytes())?),
+            None => request_builder.body(fuzzed_body),
+        };
     }
 
     for header_str in headers {
@@ -140,8 +937,8 @@ pub async fn perform_scan(
             let header_name = parts[0].trim();
             let mut header_value = parts[1].trim().to_string();
 
-            if header_value.contains("FUZZ") {
-                header_value = header_value.replace("FUZZ", word);
+            if header_value.contains(keyword) {
+                header_value = substitute(&header_value, keyword, word);
             }
             request_builder = request_builder.header(header_name, header_value);
         } else {
@@ -149,166 +946,550 @@ pub async fn perform_scan(
         }
     }
 
-    let res = request_builder.send().await;
-    let res = match res {
-        Ok(r) => {
-            tx.send(ScanEvent::RequestCompleted).await?;
-            r
+    Ok((target_url, request_builder))
+}
+
+pub async fn perform_scan(
+    client: &Client,
+    base_url: &url::Url,
+    word: &str,
+    tx: Sender<ScanEvent>, // Changed to ScanEvent
+    options: &ScanConfig,
+) -> Result<Option<(url::Url, u16)>> {
+    let ScanConfig {
+        http_method,
+        exclude_status,
+        include_status,
+        exact_words,
+        exact_chars,
+        exact_lines,
+        exclude_exact_words,
+        exclude_exact_chars,
+        exclude_exact_lines,
+        fuzz_mode,
+        data,
+        ..
+    } = options;
+    let mut jittered_headers;
+    let headers = if options.header_jitter {
+        jittered_headers = options.headers.clone();
+        options.rng.with(|rng| jittered_headers.shuffle(rng));
+        &jittered_headers
+    } else {
+        &options.headers
+    };
+    let over_budget = options
+        .max_total_bytes
+        .is_some_and(|max| options.downloaded_bytes.load(Ordering::SeqCst) > max);
+    let head_only = options.head_first
+        && !options.reads_body()
+        && [
+            exact_words,
+            exact_chars,
+            exact_lines,
+            exclude_exact_words,
+            exclude_exact_chars,
+            exclude_exact_lines,
+        ]
+        .iter()
+        .all(|counts| counts.is_none());
+    let http_method = match http_method {
+        HttpMethod::GET if over_budget || head_only => &HttpMethod::HEAD,
+        http_method => http_method,
+    };
+    if let Some(condition) = &options.conditional_body
+        && matches!(http_method, HttpMethod::POST)
+    {
+        // Probe the fuzzed URL with a GET first; the body is only sent if it matches.
+        let (_, probe) =
+            build_request(client, base_url, word, &HttpMethod::GET, fuzz_mode, headers, data, options)?;
+        let (res, elapsed) = send_request(client, probe.build()?, &tx, options).await?;
+        let probe_body = read_body(res, options).await?;
+        if !condition.is_match(&probe_body) {
+            tx.send(ScanEvent::RequestCompleted { elapsed }).await?;
+            return Ok(None);
         }
-        Err(e) => {
-            tx.send(ScanEvent::ErrorOccurred(e.to_string())).await?;
-            return Err(e.into());
+    }
+    let (target_url, mut request_builder) = build_request(
+        client,
+        base_url,
+        word,
+        http_method,
+        fuzz_mode,
+        headers,
+        data,
+        options,
+    )?;
+    if options.random_agent && !has_header(headers, "user-agent") {
+        let user_agent = options
+            .rng
+            .with(|rng| USER_AGENTS[rng.random_range(0..USER_AGENTS.len())]);
+        request_builder = request_builder.header(reqwest::header::USER_AGENT, user_agent);
+    }
+    let mut request_id = None;
+    if let Some(name) = &options.request_id_header {
+        let id = options.request_ids.fetch_add(1, Ordering::SeqCst) + 1;
+        request_builder = request_builder.header(name, id);
+        request_id = Some((name, id));
+    }
+    if let Some((user, password)) = &options.basic_auth
+        && !has_header(headers, "authorization")
+    {
+        request_builder = request_builder.basic_auth(user, Some(password));
+    }
+    let cookies = options
+        .cookies
+        .iter()
+        .map(|cookie| substitute(cookie, options.fuzz_keyword(), word))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    let mut request = request_builder.build()?;
+    // Cookies given with -H or --from-browser go into the same header as the others.
+    let given_cookies = request
+        .headers()
+        .get_all(reqwest::header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join("; ");
+    if let Some(header) = cookie_header(&target_url, &cookies, &given_cookies, options).await {
+        request
+            .headers_mut()
+            .insert(reqwest::header::COOKIE, header.parse()?);
+    }
+    let method = request.method().to_string();
+    let mut har_entry = options.har.as_ref().map(|_| har::Entry::new(&request));
+    let follow_template = options.follow_redirects.and_then(|_| request.try_clone());
+    let (res, elapsed) = send_request(client, request, &tx, options).await?;
+    tx.send(ScanEvent::RequestCompleted { elapsed }).await?;
+    if let Some(threshold) = options.slow_threshold
+        && elapsed > threshold
+    {
+        tx.send(ScanEvent::Warning(format!(
+            "Slow response: {} took {} ms, more than {} ms.",
+            target_url,
+            elapsed.as_millis(),
+            threshold.as_millis()
+        )))
+        .await?;
+    }
+    let (final_url, res, ttfb, redirect_chain) = match (options.follow_redirects, follow_template) {
+        (Some(max_redirects), Some(template)) => {
+            follow_redirects(
+                client,
+                template,
+                (res, elapsed),
+                max_redirects,
+                (&cookies, &given_cookies),
+                &tx,
+                options,
+            )
+            .await?
         }
+        _ => (target_url.clone(), res, elapsed, Vec::new()),
     };
 
+    if let Some(jar) = &options.cookie_jar {
+        jar.store(&final_url, res.headers()).await;
+    }
 
     let status = res.status();
     let status_code = status.as_u16();
     let url_str = target_url.to_string();
-
-    let redirect_target = if status_code == 301 {
+    if let Some(entry) = &mut har_entry {
+        entry.set_response(&res, ttfb);
+    }
+    // Only kept when needed, since the response is consumed by reading the body.
+    let response_headers = (!options.match_has_headers.is_empty()
+        || !options.filter_has_headers.is_empty())
+    .then(|| res.headers().clone());
+    let is_html = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("html"));
+
+    // Relative targets are resolved against the URL that answered with the redirect.
+    let redirect_target = if status.is_redirection() {
         res.headers()
-            .get("Location")
+            .get(reqwest::header::LOCATION)
             .and_then(|h| h.to_str().ok())
-            .unwrap_or("unknown")
-            .to_string()
+            .map(|location| match final_url.join(location) {
+                Ok(resolved) => resolved.to_string(),
+                Err(_) => location.to_string(),
+            })
     } else {
-        String::new()
+        None
     };
+    // A redirect to another host, or one followed there, makes that URL the base of
+    // the next level, so `start_scan` can check it against the scope.
+    let offsite_url = redirect_target
+        .as_deref()
+        .and_then(|target| url::Url::parse(target).ok())
+        .unwrap_or_else(|| final_url.clone());
+    let offsite_url = (offsite_url.host_str() != target_url.host_str()).then_some(offsite_url);
 
     // Filtering logic: include_status takes precedence over exclude_status
-    if let Some(include) = include_status {
-        if !include.contains(&status_code) {
-            return Ok(None);
-        }
-    } else if let Some(exclude) = exclude_status {
-        if exclude.contains(&status_code) {
-            return Ok(None);
+    let status_reported = match (include_status, exclude_status) {
+        (Some(include), _) => include.contains(&status_code),
+        (None, Some(exclude)) => !exclude.contains(&status_code),
+        (None, None) => status_code != 404,
+    };
+    // In `MatchMode::Any`, a body condition can still report a response whose status
+    // is not included.
+    let body_conditions = exact_words.is_some()
+        || exact_chars.is_some()
+        || exact_lines.is_some()
+        || !options.word_range.is_unbounded()
+        || !options.char_range.is_unbounded()
+        || !options.line_range.is_unbounded()
+        || options.match_regex.is_some()
+        || !options.match_has_headers.is_empty();
+    let status_may_be_outvoted =
+        options.match_mode == MatchMode::Any && include_status.is_some() && body_conditions;
+    let ttfb_reported = options.ttfb_range.contains(ttfb.as_millis() as usize);
+    let content_type_reported = content_type_reported(res.headers(), options);
+    if (!status_reported && !status_may_be_outvoted) || !ttfb_reported || !content_type_reported {
+        if !options.har_hits_only {
+            options.record_har(har_entry).await;
         }
-    } else if status_code == 404 {
         return Ok(None);
     }
 
-    let body = res.text().await?;
-    let (words_count, chars_count, lines_count) = if status_code == 301 {
-        (0, 0, 0)
+    // Redirect bodies, and those of `no_body_for`, are reported as empty, so they are
+    // not downloaded at all.
+    let received_at = std::time::Instant::now();
+    let body = if options.skips_body(status) || over_budget {
+        String::new()
     } else {
-        let w = body.split_whitespace().count();
-        let c = body.chars().count();
-        let l = body.lines().count();
-        (w, c, l)
-    };
-
-    if let Some(exact_w_list) = exact_words {
-        if !exact_w_list.contains(&words_count) {
-            return Ok(None);
+        match read_body(res, options).await {
+            Ok(body) => body,
+            Err(e) if e.is::<SlowBody>() => {
+                tx.send(ScanEvent::ErrorOccurred(format!(
+                    "{}: {}: {}",
+                    RequestErrorKind::SlowBody,
+                    target_url,
+                    e
+                )))
+                .await?;
+                return Err(e);
+            }
+            Err(e) => return Err(e),
         }
+    };
+    let total = ttfb + received_at.elapsed();
+    let received = body.len() as u64;
+    let downloaded = options.downloaded_bytes.fetch_add(received, Ordering::SeqCst) + received;
+    if let Some(max) = options.max_total_bytes
+        && downloaded > max
+        && downloaded - received <= max
+    {
+        tx.send(ScanEvent::Warning(format!(
+            "Downloaded {} bytes, more than the budget of {} bytes; \
+             sending HEAD requests and not reading response bodies from now on.",
+            downloaded, max
+        )))
+        .await?;
     }
-    if let Some(exact_c_list) = exact_chars {
-        if !exact_c_list.contains(&chars_count) {
-            return Ok(None);
-        }
+    if let Some(entry) = &mut har_entry {
+        entry.set_body(&body, received_at.elapsed());
     }
-    if let Some(exact_l_list) = exact_lines {
-        if !exact_l_list.contains(&lines_count) {
-            return Ok(None);
-        }
+    if !options.har_hits_only {
+        options.record_har(har_entry.take()).await;
+    }
+    // Without a body (HEAD, or the byte budget is spent), the counts are unknown
+    // rather than zero.
+    let body_read = !matches!(http_method, HttpMethod::HEAD) && !over_budget;
+    let counted = |count: usize| body_read.then_some(count);
+    let words_count = counted(body.split_whitespace().count());
+    let chars_count = counted(body.chars().count());
+    let lines_count = counted(body.lines().count());
+
+    if !passes_filters(
+        status_code,
+        words_count,
+        chars_count,
+        lines_count,
+        Some(&body),
+        response_headers.as_ref(),
+        include_status,
+        exclude_status,
+        exact_words,
+        exact_chars,
+        exact_lines,
+        exclude_exact_words,
+        exclude_exact_chars,
+        exclude_exact_lines,
+        options,
+    ) {
+        return Ok(None);
     }
 
-    if let Some(exclude_exact_w_list) = exclude_exact_words {
-        if exclude_exact_w_list.contains(&words_count) {
-            return Ok(None);
-        }
+    let signature = ResponseSignature {
+        status: status_code,
+        chars: chars_count.unwrap_or_default(),
+    };
+    if options
+        .baseline
+        .is_some_and(|baseline| baseline.matches(&signature, options.baseline_threshold))
+    {
+        return Ok(None);
     }
-    if let Some(exclude_exact_c_list) = exclude_exact_chars {
-        if exclude_exact_c_list.contains(&chars_count) {
+    if !options.calibrated.is_empty() {
+        let signature = CalibratedSignature::new(status_code, &body);
+        if options.calibrated.iter().any(|soft_404| soft_404.matches(&signature)) {
             return Ok(None);
         }
     }
-    if let Some(exclude_exact_l_list) = exclude_exact_lines {
-        if exclude_exact_l_list.contains(&lines_count) {
+
+    // Responses without a body (HEAD, or over the byte budget) cannot be told apart,
+    // so they are never deduplicated.
+    if options.dedupe_by_body_hash
+        && !options.skips_body(status)
+        && !over_budget
+        && !matches!(http_method, HttpMethod::HEAD)
+    {
+        let mut hasher = Sha1::new();
+        hasher.update(status_code.to_be_bytes());
+        hasher.update(body.as_bytes());
+        let digest: [u8; 20] = hasher.finalize().into();
+        if !options.body_hashes.lock().await.insert(digest) {
+            tx.send(ScanEvent::Warning(format!(
+                "{} has the same response as an earlier hit; not reported.",
+                url_str
+            )))
+            .await?;
             return Ok(None);
         }
     }
 
-    let formatted_output = match status_code {
-        301 => format!(
-            "[{}] {} -> {} [{}W, {}C, {}L]",
-            status, url_str, redirect_target, words_count, chars_count, lines_count
-        ),
-        _ => format!(
-            "[{}] {} [{}W, {}C, {}L]",
-            status, url_str, words_count, chars_count, lines_count
-        ),
+    let client_redirect = if options.follow_meta_refresh
+        && is_html
+        && status.is_success()
+        && *fuzz_mode == FuzzMode::Path
+    {
+        find_client_redirect(&body, &target_url)
+    } else {
+        None
+    };
+
+    let finding = Finding {
+        url: url_str,
+        status: status_code,
+        redirect_target: match (redirect_target, &client_redirect) {
+            (Some(target), _) => Some(target),
+            (None, Some(client_target)) => Some(client_target.to_string()),
+            (None, None) => None,
+        },
+        words: words_count,
+        chars: chars_count,
+        lines: lines_count,
+        method,
+        timestamp: har::format_timestamp(std::time::SystemTime::now()),
+        classification: Classification::from_status(status_code),
+        redirects: redirect_chain.len().saturating_sub(1),
+        ttfb_ms: ttfb.as_millis() as u64,
+        total_ms: total.as_millis() as u64,
+        redirect_chain,
+        client_redirect: !status.is_redirection() && client_redirect.is_some(),
+        request_id: request_id.map(|(header, id)| RequestId {
+            header: header.clone(),
+            id,
+        }),
+        word: options.show_word.then(|| word.replace(PAYLOAD_SEPARATOR, ", ")),
     };
 
-    tx.send(ScanEvent::FoundUrl(formatted_output.trim().to_string())).await?; // Changed to ScanEvent
+    options.record_har(har_entry).await;
+    tx.send(ScanEvent::FoundUrl(finding)).await?; // Changed to ScanEvent
+
+    // Client-side redirects are only followed within the same origin.
+    if let Some(client_target) = client_redirect
+        && client_target.origin() == target_url.origin()
+    {
+        options.client_redirects.lock().await.push(client_target);
+    }
 
     // If the status is success, we've found something.
-    // We'll return it as a potential base for the next level of scanning.
+    // We'll return it, with its status, as a potential base for the next level of scanning.
+    let next_url = offsite_url.unwrap_or(target_url);
     if status.is_success() {
-        let mut new_base_url = target_url;
+        let mut new_base_url = next_url;
         // Ensure the path ends with a '/' to allow for deeper scanning.
         if !new_base_url.path().ends_with('/') {
             let mut path = new_base_url.path().to_string();
             path.push('/');
             new_base_url.set_path(&path);
         }
-        Ok(Some(new_base_url))
+        Ok(Some((new_base_url, status_code)))
     } else if status.is_redirection() {
         // For redirects, we also consider it for further scanning.
-        Ok(Some(target_url))
+        Ok(Some((next_url, status_code)))
     } else {
         Ok(None)
     }
 }
 
+/// Scans `base_url` with `words` as configured, sending the results to `tx`, until the
+/// queue is done or a [`ControlEvent::Stop`] arrives on `ctrl_rx`.
+///
+/// ```no_run
+/// use dirnutek::{ScanConfig, ScanEvent, start_scan};
+/// use std::collections::HashSet;
+/// use tokio::sync::{broadcast, mpsc};
+///
+/// # async fn scan() -> anyhow::Result<()> {
+/// let (tx, mut rx) = mpsc::channel(100);
+/// let (_stop_tx, stop_rx) = broadcast::channel(1);
+/// let config = ScanConfig {
+///     concurrency: 10,
+///     include_status: Some(HashSet::from([200, 301])),
+///     max_depth: 2,
+///     ..Default::default()
+/// };
+/// let url = url::Url::parse("http://example.com/")?;
+/// let words = vec!["admin".to_string()];
+/// tokio::spawn(start_scan(reqwest::Client::new(), url, words, tx, stop_rx, config));
+/// while let Some(event) = rx.recv().await {
+///     if let ScanEvent::FoundUrl(finding) = event {
+///         println!("{}", finding);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
 pub async fn start_scan(
     client: Client,
     base_url: url::Url,
     words: Vec<String>,
     tx: Sender<ScanEvent>,
-    visited_urls: Arc<Mutex<HashSet<url::Url>>>,
     mut ctrl_rx: broadcast::Receiver<ControlEvent>,
-    concurrency: usize,
-    http_method: HttpMethod,
-    exclude_status: Option<HashSet<u16>>,
-    include_status: Option<HashSet<u16>>,
-    max_depth: usize,
-    delay: Option<u64>,
-    exact_words: Option<Vec<usize>>,
-    exact_chars: Option<Vec<usize>>,
-    exact_lines: Option<Vec<usize>>,
-    exclude_exact_words: Option<Vec<usize>>,
-    exclude_exact_chars: Option<Vec<usize>>,
-    exclude_exact_lines: Option<Vec<usize>>,
-    fuzz_mode: FuzzMode,
-    headers: Vec<String>,
-    data: Option<String>,
+    options: ScanConfig,
 ) -> Result<()> {
+    let concurrency = options.concurrency.max(1);
+    let max_depth = options.max_depth;
+    let visited_urls = options.visited_urls.clone();
     let semaphore = Arc::new(Semaphore::new(concurrency));
-    let scan_delay_for_loop = delay.clone();
-    let scan_queue: Arc<Mutex<VecDeque<(url::Url, usize)>>> = Arc::new(Mutex::new(VecDeque::new()));
+    let current_concurrency = Arc::new(AtomicUsize::new(concurrency));
+    let goaway_count = Arc::new(AtomicUsize::new(0));
+    // Milliseconds from `scan_started` to the last completed request, for `idle_timeout`.
+    let scan_started = std::time::Instant::now();
+    let last_activity = Arc::new(AtomicU64::new(0));
+    let mut scan_delay_for_loop = options.delay;
+    let mut burst_sent = 0;
+    let scan_queue: Arc<Mutex<VecDeque<ScanJob>>> = Arc::new(Mutex::new(VecDeque::new()));
     let mut join_set: JoinSet<Result<()>> = JoinSet::new();
+    let failed_words: Arc<Mutex<Vec<(url::Url, usize, String)>>> = Arc::new(Mutex::new(Vec::new()));
+    let recovered_words = Arc::new(AtomicUsize::new(0));
+    let mut replaying = false;
+    let mut replayed_count = 0;
+    // The depth of the last dequeued directory, to detect new levels for `level_delay`.
+    let mut last_depth = 0;
+    // The directories found with `recursion_preview`, which are not scanned.
+    let mut previewed = Vec::new();
+    let visited_bloom = if options.low_memory {
+        Some(Arc::new(Mutex::new(BloomFilter::default())))
+    } else {
+        None
+    };
+    let mut options = options;
+    // Set once the first off-site URL has been kept out of recursion.
+    let offsite_warned = Arc::new(AtomicBool::new(false));
+
+    let total_words = count_scan_words(&words, &options.fuzz_mode, &options).await?;
 
     // Send ScanStarted event
-    tx.send(ScanEvent::ScanStarted {
-        total_words: words.len(),
-    })
-    .await?;
+    tx.send(ScanEvent::ScanStarted { total_words }).await?;
+    if options.basic_auth.is_some() && has_header(&options.headers, "authorization") {
+        tx.send(ScanEvent::Warning(
+            "An Authorization header is set; it is sent instead of the --auth credentials."
+                .to_string(),
+        ))
+        .await?;
+    }
+
+    if options.vhost_baseline || options.detect_waf {
+        let preflight = preflight(
+            &client,
+            &base_url,
+            &tx,
+            &options.http_method,
+            &options.fuzz_mode,
+            &options.headers,
+            &options.data,
+            &options,
+        )
+        .await?;
+        options.baseline = preflight.baseline;
+        if options.adapt_to_waf && preflight.waf.is_some() {
+            let reduced = concurrency.min(WAF_MAX_CONCURRENCY);
+            semaphore.forget_permits(concurrency - reduced);
+            current_concurrency.store(reduced, Ordering::SeqCst);
+            let delay = scan_delay_for_loop.unwrap_or_default().max(WAF_MIN_DELAY_MS);
+            scan_delay_for_loop = Some(delay);
+            tx.send(ScanEvent::Warning(format!(
+                "Adapting to the WAF: concurrency {}, {} ms delay between requests.",
+                reduced, delay
+            )))
+            .await?;
+        }
+    }
+    if let Some(baseline_url) = &options.baseline_url {
+        options.baseline = fetch_baseline(&client, &base_url.join(baseline_url)?, &tx, &options).await?;
+    }
+    if options.auto_filter {
+        options.calibrated = calibrate(
+            &client,
+            &base_url,
+            &tx,
+            &options.http_method,
+            &options.fuzz_mode,
+            &options.headers,
+            &options.data,
+            &options,
+        )
+        .await?;
+    }
+    let options = Arc::new(options);
 
     // Initial push to the queue
-    scan_queue.lock().await.push_back((base_url.clone(), 0));
+    scan_queue.lock().await.push_back(ScanJob::new(base_url.clone(), 0));
 
     'main_loop: loop {
         // Dequeue a URL to scan if available
-        let (current_url, current_depth) = {
+        let job = {
             let mut queue = scan_queue.lock().await;
-            if let Some(item) = queue.pop_front() {
+            let next = if options.depth_first {
+                queue.pop_back()
+            } else {
+                queue.pop_front()
+            };
+            if let Some(item) = next {
                 item
             } else if join_set.is_empty() {
-                // If queue is empty and no active scans, we are done
-                break;
+                // The main pass is done; queue the failed words once if requested.
+                let failed = std::mem::take(&mut *failed_words.lock().await);
+                if replaying || failed.is_empty() {
+                    // If queue is empty and no active scans, we are done
+                    break;
+                }
+                replaying = true;
+                replayed_count = failed.len();
+                for (url, depth, word) in failed {
+                    match queue.iter_mut().find(|job| job.replayed && job.url == url && job.depth == depth) {
+                        Some(job) => job.words.get_or_insert_with(Vec::new).push(word),
+                        None => queue.push_back(ScanJob {
+                            url,
+                            depth,
+                            words: Some(vec![word]),
+                            replayed: true,
+                        }),
+                    }
+                }
+                drop(queue);
+                tokio::time::sleep(tokio::time::Duration::from_millis(options.replay_backoff))
+                    .await;
+                continue;
             } else {
                 // Queue is empty but scans are active, wait for one to complete
                 // This allows new URLs to be added to the queue by other tasks
@@ -320,105 +1501,321 @@ pub async fn start_scan(
                         join_set.abort_all();
                         break 'main_loop;
                     }
-                    _ = join_set.join_next() => {
+                    res = join_set.join_next() => {
                         // A task finished, maybe it populated the queue.
                         // The loop will continue and check the queue again.
+                        if let Some(res) = res {
+                            reap(res, &tx).await?;
+                        }
+                    }
+                    _ = idle_deadline(scan_started, &last_activity, options.idle_timeout) => {
+                        abort_stalled(&mut join_set, scan_started, &last_activity, &tx, &options).await?;
                     }
                 }
                 continue;
             }
         };
 
+        let current_url = job.url;
+        let current_depth = job.depth;
+        let replayed_job = job.replayed;
+        if options.recursion_preview && current_depth > 0 {
+            previewed.push(current_url.to_string());
+            continue;
+        }
         if max_depth > 0 && current_depth >= max_depth {
             continue;
         }
-
-        for word in &words {
-            let permit = tokio::select! {
+        if let Some((min, max)) = options.level_delay
+            && current_depth > last_depth
+        {
+            // Let the previous level finish, so the pause is a real gap in the traffic.
+            while !join_set.is_empty() {
+                tokio::select! {
+                    biased;
+                    _ = ctrl_rx.recv() => {
+                        tx.send(ScanEvent::ScanStopped).await?;
+                        join_set.abort_all();
+                        break 'main_loop;
+                    }
+                    Some(res) = join_set.join_next() => reap(res, &tx).await?,
+                }
+            }
+            let pause = options.rng.with(|rng| rng.random_range(min..=max));
+            let pause = Duration::from_millis(pause);
+            tx.send(ScanEvent::LevelPaused {
+                depth: current_depth,
+                pause,
+            })
+            .await?;
+            tokio::select! {
                 biased;
                 _ = ctrl_rx.recv() => {
                     tx.send(ScanEvent::ScanStopped).await?;
                     join_set.abort_all();
                     break 'main_loop;
                 }
-                permit = semaphore.clone().acquire_owned() => {
-                    permit.expect("Failed to acquire semaphore permit")
+                _ = tokio::time::sleep(pause) => {}
+            }
+        }
+        last_depth = current_depth;
+
+        // The position of the next word in its list, to pair it for `pitchfork`.
+        let (mut word_source, mut word_index) = match (&job.words, &options.recursion_words) {
+            (Some(job_words), _) => (WordSource::Memory(job_words.iter()), 0),
+            (None, Some(recursion_words)) if current_depth > 0 => {
+                (WordSource::Memory(recursion_words.iter()), 0)
+            }
+            (None, _) => (WordSource::for_scan(&words, &options).await?, options.skip_words),
+        };
+        while let Some(base_word) = word_source.next_word().await? {
+            // Replayed words were combined and expanded in the main pass already.
+            let variants = match job.words {
+                Some(_) => vec![base_word],
+                None => {
+                    let combined: Vec<String> = expand_word(base_word, &options.fuzz_mode, &options)
+                        .into_iter()
+                        .flat_map(|word| combine_words(word, word_index, &options))
+                        .collect();
+                    word_index += 1;
+                    if combined.is_empty() {
+                        // The shortest pitchfork list is exhausted.
+                        break;
+                    }
+                    combined
                 }
             };
-
-            let client_clone = client.clone();
-            let current_url_clone = current_url.clone();
-            let tx_clone = tx.clone();
-            let exclude_status_clone = exclude_status.clone();
-            let include_status_clone = include_status.clone();
-            let word_clone = word.clone();
-            let visited_urls_clone = visited_urls.clone();
-            let scan_queue_clone = scan_queue.clone();
-            let scan_delay_clone = scan_delay_for_loop.clone();
-            let http_method_clone = http_method.clone();
-            let exact_words_clone = exact_words.clone();
-            let exact_chars_clone = exact_chars.clone();
-            let exact_lines_clone = exact_lines.clone();
-            let exclude_exact_words_clone = exclude_exact_words.clone();
-            let exclude_exact_chars_clone = exclude_exact_chars.clone();
-            let exclude_exact_lines_clone = exclude_exact_lines.clone();
-            let fuzz_mode_clone = fuzz_mode.clone();
-            let headers_clone = headers.clone();
-            let data_clone = data.clone();
-
-            join_set.spawn(async move {
-                if let Some(d) = scan_delay_clone {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(d)).await;
+            for word in variants {
+                if let Some(burst) = options.burst
+                    && burst_sent == burst.requests
+                {
+                    tx.send(ScanEvent::BurstPaused { pause: burst.pause }).await?;
+                    tokio::select! {
+                        biased;
+                        _ = ctrl_rx.recv() => {
+                            tx.send(ScanEvent::ScanStopped).await?;
+                            join_set.abort_all();
+                            break 'main_loop;
+                        }
+                        _ = tokio::time::sleep(burst.pause) => {}
+                    }
+                    tx.send(ScanEvent::BurstResumed).await?;
+                    burst_sent = 0;
                 }
+                burst_sent += 1;
 
-                let result = perform_scan(
-                    &client_clone,
-                    &current_url_clone,
-                    &word_clone,
-                    tx_clone, // Pass the new Sender
-                    &http_method_clone,
-                    &exclude_status_clone,
-                    &include_status_clone,
-                    scan_delay_clone,
-                    exact_words_clone,
-                    exact_chars_clone,
-                    exact_lines_clone,
-                    exclude_exact_words_clone,
-                    exclude_exact_chars_clone,
-                    exclude_exact_lines_clone,
-                    &fuzz_mode_clone,
-                    &headers_clone,
-                    &data_clone,
-                )
-                .await;
-                
-                drop(permit);
-
-
-                if let Ok(Some(found_url)) = result {
-                    let mut visited = visited_urls_clone.lock().await;
-                    if visited.insert(found_url.clone()) {
-                        if current_depth < max_depth {
-                            scan_queue_clone
-                                .lock()
-                                .await
-                                .push_back((found_url, current_depth + 1));
-                        }
+                let permit = tokio::select! {
+                    biased;
+                    _ = ctrl_rx.recv() => {
+                        tx.send(ScanEvent::ScanStopped).await?;
+                        join_set.abort_all();
+                        break 'main_loop;
+                    }
+                    permit = semaphore.clone().acquire_owned() => {
+                        permit.expect("Failed to acquire semaphore permit")
                     }
-                } else if let Err(e) = result {
-                    eprintln!(
-                        "Error from perform_scan for {} + {}: {:?}",
-                        current_url_clone, word_clone, e
+                    // Aborting the stalled tasks releases their permits.
+                    _ = idle_deadline(scan_started, &last_activity, options.idle_timeout) => {
+                        abort_stalled(&mut join_set, scan_started, &last_activity, &tx, &options).await?;
+                        semaphore.clone().acquire_owned().await.expect("Failed to acquire semaphore permit")
+                    }
+                };
+
+                let client_clone = client.clone();
+                let current_url_clone = current_url.clone();
+                let tx_clone = tx.clone();
+                let word_clone = word.clone();
+                let visited_urls_clone = visited_urls.clone();
+                let scan_queue_clone = scan_queue.clone();
+                let scan_delay_clone = scan_delay_for_loop;
+                let options_clone = options.clone();
+                let visited_bloom_clone = visited_bloom.clone();
+                let failed_words_clone = failed_words.clone();
+                let recovered_words_clone = recovered_words.clone();
+                let semaphore_clone = semaphore.clone();
+                let current_concurrency_clone = current_concurrency.clone();
+                let goaway_count_clone = goaway_count.clone();
+                let tx_task = tx.clone();
+                let last_activity_clone = last_activity.clone();
+                let base_url_clone = base_url.clone();
+                let offsite_warned_clone = offsite_warned.clone();
+
+                join_set.spawn(async move {
+                    if let Some(d) = scan_delay_clone {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(d)).await;
+                    }
+
+                    let result = perform_scan(
+                        &client_clone,
+                        &current_url_clone,
+                        &word_clone,
+                        tx_clone, // Pass the new Sender
+                        &options_clone,
+                    )
+                    .await;
+                    last_activity_clone.store(
+                        scan_started.elapsed().as_millis() as u64,
+                        Ordering::SeqCst,
                     );
+
+                    drop(permit);
+
+                    if replayed_job && result.is_ok() {
+                        recovered_words_clone.fetch_add(1, Ordering::SeqCst);
+                    }
+
+                    let client_redirects =
+                        std::mem::take(&mut *options_clone.client_redirects.lock().await);
+                    for client_target in client_redirects {
+                        let newly_visited = match &visited_bloom_clone {
+                            Some(bloom) => bloom.lock().await.insert(client_target.as_str()),
+                            None => visited_urls_clone.lock().await.insert(client_target.clone()),
+                        };
+                        if newly_visited {
+                            // Request the target once, as a single word relative to the site root.
+                            let word = client_target[url::Position::BeforePath..]
+                                .trim_start_matches('/')
+                                .to_string();
+                            let mut root = client_target;
+                            root.set_path("/");
+                            root.set_query(None);
+                            root.set_fragment(None);
+                            scan_queue_clone.lock().await.push_back(ScanJob {
+                                url: root,
+                                depth: current_depth,
+                                words: Some(vec![word]),
+                                replayed: false,
+                            });
+                        }
+                    }
+
+                    if let Ok(Some((found_url, status))) = result {
+                        let recurse_status = options_clone
+                            .recurse_status
+                            .as_ref()
+                            .is_none_or(|statuses| statuses.contains(&status));
+                        let excluded_path = options_clone
+                            .no_recurse_paths
+                            .iter()
+                            .any(|excluded| found_url.path().contains(excluded.as_str()));
+                        let offsite = !options_clone.allow_offsite_recursion
+                            && !in_scope(
+                                &found_url,
+                                &base_url_clone,
+                                &options_clone.fuzz_mode,
+                                options_clone.fuzz_keyword(),
+                            );
+                        if offsite && !offsite_warned_clone.swap(true, Ordering::SeqCst) {
+                            tx_task
+                                .send(ScanEvent::Warning(format!(
+                                    "Not recursing into {}: it is not on the scanned host. \
+                                     Use --allow-offsite-recursion to follow it.",
+                                    found_url
+                                )))
+                                .await?;
+                        }
+                        if recurse_status && !excluded_path && !offsite {
+                            let newly_visited = match &visited_bloom_clone {
+                                Some(bloom) => bloom.lock().await.insert(found_url.as_str()),
+                                None => visited_urls_clone.lock().await.insert(found_url.clone()),
+                            };
+                            let too_long = options_clone
+                                .max_url_length
+                                .is_some_and(|limit| found_url.as_str().len() > limit);
+                            if newly_visited
+                                && (current_depth < max_depth || options_clone.recursion_preview)
+                                && too_long
+                            {
+                                tx_task
+                                    .send(ScanEvent::Warning(format!(
+                                        "Not recursing into {}: URL is longer than {} characters.",
+                                        found_url,
+                                        options_clone.max_url_length.unwrap_or_default()
+                                    )))
+                                    .await?;
+                            } else if newly_visited
+                                && (current_depth < max_depth || options_clone.recursion_preview)
+                            {
+                                scan_queue_clone
+                                    .lock()
+                                    .await
+                                    .push_back(ScanJob::new(found_url, current_depth + 1));
+                            }
+                        }
+                    } else if let Err(e) = result {
+                        eprintln!(
+                            "Error from perform_scan for {} + {}: {:?}",
+                            current_url_clone, word_clone, e
+                        );
+                        let reqwest_error = e.downcast_ref::<reqwest::Error>();
+                        if options_clone.adaptive_throttle
+                            && reqwest_error.map(classify_error) == Some(RequestErrorKind::GoAway)
+                            && (goaway_count_clone.fetch_add(1, Ordering::SeqCst) + 1)
+                                .is_multiple_of(GOAWAY_THROTTLE_THRESHOLD)
+                        {
+                            reduce_concurrency(
+                                &semaphore_clone,
+                                &current_concurrency_clone,
+                                &tx_task,
+                            )
+                            .await?;
+                        }
+                        if options_clone.replay_failed
+                            && !replaying
+                            && reqwest_error
+                                .is_some_and(|e| options_clone.retry_classifier.is_retryable(e))
+                        {
+                            failed_words_clone.lock().await.push((
+                                current_url_clone,
+                                current_depth,
+                                word_clone,
+                            ));
+                        }
+                    }
+                    Ok(())
+                });
+
+                // Reap finished tasks so the join set does not grow with the wordlist.
+                while let Some(res) = join_set.try_join_next() {
+                    reap(res, &tx).await?;
                 }
-                Ok(())
-            });
+            }
         }
     }
 
     // Wait for any remaining tasks in the join_set to complete
-    while let Some(res) = join_set.join_next().await {
-        res??;
+    loop {
+        tokio::select! {
+            res = join_set.join_next() => match res {
+                Some(res) => reap(res, &tx).await?,
+                None => break,
+            },
+            _ = idle_deadline(scan_started, &last_activity, options.idle_timeout) => {
+                abort_stalled(&mut join_set, scan_started, &last_activity, &tx, &options).await?;
+            }
+        }
+    }
+    // Wakes up any pending permit retirement from `reduce_concurrency`.
+    semaphore.close();
+
+    if replaying {
+        tx.send(ScanEvent::ReplayFinished {
+            replayed: replayed_count,
+            recovered: recovered_words.load(Ordering::SeqCst),
+        })
+        .await?;
+    }
+
+    if options.recursion_preview {
+        let words_per_directory = match &options.recursion_words {
+            Some(recursion_words) => recursion_words.len(),
+            None => total_words,
+        };
+        tx.send(ScanEvent::RecursionPreview {
+            requests: previewed.len().saturating_mul(words_per_directory),
+            directories: previewed,
+        })
+        .await?;
     }
 
     // Send ScanFinished event
@@ -428,6 +1825,924 @@ pub async fn start_scan(
 
     Ok(())
 }
+
+static URL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"https?://[^\s]+").unwrap());
+
+/// Extracts the http(s) URLs from a line of text, such as a reported result
+/// (`[200] http://example.com/admin [5W, 20C, 1L]`), in order of appearance.
+pub fn extract_urls(text: &str) -> impl Iterator<Item = &str> {
+    URL_REGEX.find_iter(text).map(|m| m.as_str())
+}
+
+/// Handles the result of a finished scan task. A task that panicked is reported as
+/// [`ScanEvent::ErrorOccurred`] so the scan goes on without it; errors returned by a
+/// task (such as a closed event channel) still end the scan.
+async fn reap(res: Result<Result<()>, JoinError>, tx: &Sender<ScanEvent>) -> Result<()> {
+    match res {
+        Err(e) if e.is_panic() => {
+            let panic = e.into_panic();
+            let message = panic
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown cause");
+            tx.send(ScanEvent::ErrorOccurred(format!("A scan task panicked: {}", message)))
+                .await?;
+            Ok(())
+        }
+        res => res?,
+    }
+}
+
+/// Resolves once no request has completed for `idle_timeout` seconds, counting from
+/// `last_activity` (milliseconds since `scan_started`). Never resolves without a timeout.
+async fn idle_deadline(
+    scan_started: std::time::Instant,
+    last_activity: &AtomicU64,
+    idle_timeout: Option<u64>,
+) {
+    let Some(idle_timeout) = idle_timeout else {
+        return std::future::pending().await;
+    };
+    loop {
+        let deadline = scan_started
+            + Duration::from_millis(last_activity.load(Ordering::SeqCst))
+            + Duration::from_secs(idle_timeout);
+        if std::time::Instant::now() >= deadline {
+            return;
+        }
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+}
+
+/// Aborts the in-flight tasks after `idle_timeout` passed without progress and waits
+/// for them to finish. Tasks that completed in the meantime are reaped as usual.
+async fn abort_stalled(
+    join_set: &mut JoinSet<Result<()>>,
+    scan_started: std::time::Instant,
+    last_activity: &AtomicU64,
+    tx: &Sender<ScanEvent>,
+    options: &ScanConfig,
+) -> Result<()> {
+    join_set.abort_all();
+    let mut aborted = 0;
+    while let Some(res) = join_set.join_next().await {
+        match res {
+            Err(e) if e.is_cancelled() => aborted += 1,
+            res => reap(res, tx).await?,
+        }
+    }
+    last_activity.store(scan_started.elapsed().as_millis() as u64, Ordering::SeqCst);
+    tx.send(ScanEvent::Warning(format!(
+        "No request completed for {} s; aborted {} stalled requests.",
+        options.idle_timeout.unwrap_or_default(),
+        aborted
+    )))
+    .await?;
+    Ok(())
+}
+
+/// Sends a single request to `url` to check that its host is reachable. Any HTTP
+/// response counts, only connection errors and timeouts fail.
+pub async fn check_connectivity(client: &Client, url: &url::Url) -> Result<(), reqwest::Error> {
+    client.get(url.as_str()).send().await.map(|_| ())
+}
+
+/// What the preflight request learned about the target.
+#[derive(Debug, Default)]
+struct Preflight {
+    /// The default response, if `vhost_baseline` is set.
+    baseline: Option<ResponseSignature>,
+    /// The WAF or CDN in front of the target, if `detect_waf` is set.
+    waf: Option<&'static str>,
+}
+
+/// Requests a word that should not exist to learn the default response of the target,
+/// e.g. the catch-all virtual host when fuzzing subdomains or the `Host` header, and
+/// which WAF or CDN answers for it.
+#[allow(clippy::too_many_arguments)]
+async fn preflight(
+    client: &Client,
+    base_url: &url::Url,
+    tx: &Sender<ScanEvent>,
+    http_method: &HttpMethod,
+    fuzz_mode: &FuzzMode,
+    headers: &[String],
+    data: &Option<String>,
+    options: &ScanConfig,
+) -> Result<Preflight> {
+    let nonce = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let bogus_word = format!("dirnutek-baseline-{}", nonce);
+    let (_, request_builder) = build_request(
+        client,
+        base_url,
+        &bogus_word,
+        http_method,
+        fuzz_mode,
+        headers,
+        data,
+        options,
+    )?;
+
+    let res = match request_builder.send().await {
+        Ok(res) => res,
+        Err(e) => {
+            let consequence = if options.vhost_baseline {
+                "all responses will be reported"
+            } else {
+                "WAF/CDN detection is skipped"
+            };
+            tx.send(ScanEvent::Warning(format!(
+                "Preflight request failed ({}); {}.",
+                e, consequence
+            )))
+            .await?;
+            return Ok(Preflight::default());
+        }
+    };
+
+    let mut result = Preflight::default();
+    if options.detect_waf {
+        result.waf = detect_waf(res.headers());
+        if let Some(product) = result.waf {
+            tx.send(ScanEvent::Warning(format!(
+                "The target appears to be behind {}; requests may be blocked or rate limited.",
+                product
+            )))
+            .await?;
+        }
+    }
+    if !options.vhost_baseline {
+        return Ok(result);
+    }
+
+    result.baseline = Some(baseline_signature(res, tx, options).await?);
+    Ok(result)
+}
+
+/// Requests `baseline_url` to learn the response of the target for a path that does
+/// not exist. Returns `None` (and warns) if the request fails.
+async fn fetch_baseline(
+    client: &Client,
+    baseline_url: &url::Url,
+    tx: &Sender<ScanEvent>,
+    options: &ScanConfig,
+) -> Result<Option<ResponseSignature>> {
+    match client.get(baseline_url.as_str()).send().await {
+        Ok(res) => Ok(Some(baseline_signature(res, tx, options).await?)),
+        Err(e) => {
+            tx.send(ScanEvent::Warning(format!(
+                "Baseline request to {} failed ({}); all responses will be reported.",
+                baseline_url, e
+            )))
+            .await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Requests [`AUTO_FILTER_PROBES`] random words and returns the distinct signatures of
+/// their responses, announcing each. Failed probes are skipped.
+#[allow(clippy::too_many_arguments)]
+async fn calibrate(
+    client: &Client,
+    base_url: &url::Url,
+    tx: &Sender<ScanEvent>,
+    http_method: &HttpMethod,
+    fuzz_mode: &FuzzMode,
+    headers: &[String],
+    data: &Option<String>,
+    options: &ScanConfig,
+) -> Result<Vec<CalibratedSignature>> {
+    let mut signatures: Vec<CalibratedSignature> = Vec::new();
+    for _ in 0..AUTO_FILTER_PROBES {
+        // Same length for every probe, so pages that echo the path keep their size.
+        let id = options.rng.with(|rng| rng.random::<u64>());
+        let word = format!("dirnutek-calibration-{:016x}", id);
+        let (_, request_builder) = build_request(
+            client,
+            base_url,
+            &word,
+            http_method,
+            fuzz_mode,
+            headers,
+            data,
+            options,
+        )?;
+        let res = match request_builder.send().await {
+            Ok(res) => res,
+            Err(e) => {
+                tx.send(ScanEvent::Warning(format!("Auto-filter probe failed ({}).", e)))
+                    .await?;
+                continue;
+            }
+        };
+        let status = res.status().as_u16();
+        let body = if options.skips_body(res.status()) {
+            String::new()
+        } else {
+            read_body(res, options).await?
+        };
+        let signature = CalibratedSignature::new(status, &body);
+        if !signatures.iter().any(|known| known.matches(&signature)) {
+            signatures.push(signature);
+        }
+    }
+
+    if signatures.is_empty() {
+        tx.send(ScanEvent::Warning(
+            "Auto-filter calibration failed; all responses will be reported.".to_string(),
+        ))
+        .await?;
+    }
+    for signature in &signatures {
+        tx.send(ScanEvent::Warning(format!(
+            "Auto-filter calibrated [{}] with {}W, {}C, {}L; matching responses will be hidden.",
+            signature.status, signature.words, signature.chars, signature.lines
+        )))
+        .await?;
+    }
+    Ok(signatures)
+}
+
+/// Computes the signature of a baseline response and announces it.
+async fn baseline_signature(
+    res: reqwest::Response,
+    tx: &Sender<ScanEvent>,
+    options: &ScanConfig,
+) -> Result<ResponseSignature> {
+    let status_code = res.status().as_u16();
+    let body = if options.skips_body(res.status()) {
+        String::new()
+    } else {
+        read_body(res, options).await?
+    };
+    let signature = ResponseSignature {
+        status: status_code,
+        chars: body.chars().count(),
+    };
+    tx.send(ScanEvent::Warning(format!(
+        "Baseline response is [{}] with {} chars; matching responses will be hidden.",
+        signature.status, signature.chars
+    )))
+    .await?;
+    Ok(signature)
+}
+
+/// Response headers that identify a WAF or CDN: header name, an optional substring of
+/// its (lowercased) value, and the product name.
+const WAF_SIGNATURES: &[(&str, Option<&str>, &str)] = &[
+    ("cf-ray", None, "Cloudflare"),
+    ("server", Some("cloudflare"), "Cloudflare"),
+    ("x-sucuri-id", None, "Sucuri"),
+    ("server", Some("sucuri"), "Sucuri"),
+    ("x-iinfo", None, "Imperva Incapsula"),
+    ("x-cdn", Some("incapsula"), "Imperva Incapsula"),
+    ("server", Some("akamaighost"), "Akamai"),
+    ("akamai-grn", None, "Akamai"),
+    ("x-amz-cf-id", None, "Amazon CloudFront"),
+    ("server", Some("awselb"), "AWS Elastic Load Balancing"),
+    ("x-azure-ref", None, "Azure Front Door"),
+    ("x-fastly-request-id", None, "Fastly"),
+    ("server", Some("bigip"), "F5 BIG-IP"),
+    ("x-datadome", None, "DataDome"),
+];
+
+/// Returns the WAF or CDN product identified by the response headers, if any.
+pub fn detect_waf(headers: &reqwest::header::HeaderMap) -> Option<&'static str> {
+    WAF_SIGNATURES
+        .iter()
+        .find(|(name, needle, _)| {
+            headers.get_all(*name).iter().any(|value| match needle {
+                Some(needle) => value
+                    .to_str()
+                    .is_ok_and(|value| value.to_ascii_lowercase().contains(needle)),
+                None => true,
+            })
+        })
+        .map(|(_, _, product)| *product)
+}
+
+/// Halves the number of concurrent requests (down to 1) by retiring semaphore permits
+/// as soon as the in-flight requests release them.
+async fn reduce_concurrency(
+    semaphore: &Arc<Semaphore>,
+    current: &AtomicUsize,
+    tx: &Sender<ScanEvent>,
+) -> Result<()> {
+    let Ok(previous) = current.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |c| {
+        (c > 1).then_some(c / 2)
+    }) else {
+        return Ok(());
+    };
+    let reduced = previous / 2;
+    let semaphore = semaphore.clone();
+    tokio::spawn(async move {
+        if let Ok(permits) = semaphore.acquire_many_owned((previous - reduced) as u32).await {
+            permits.forget();
+        }
+    });
+    tx.send(ScanEvent::Warning(format!(
+        "Server sent repeated HTTP/2 GOAWAY frames; reducing concurrency from {} to {}.",
+        previous, reduced
+    )))
+    .await?;
+    Ok(())
+}
+
+/// A directory waiting to be scanned. `words` replaces the wordlist for this directory.
+struct ScanJob {
+    url: url::Url,
+    depth: usize,
+    words: Option<Vec<String>>,
+    /// Whether `words` are failed words queued again by `replay_failed`.
+    replayed: bool,
+}
+
+impl ScanJob {
+    fn new(url: url::Url, depth: usize) -> Self {
+        Self {
+            url,
+            depth,
+            words: None,
+            replayed: false,
+        }
+    }
+}
+
+/// Counts the non-empty lines of a wordlist file without loading it into memory.
+pub async fn count_words(path: &Path) -> Result<usize> {
+    let mut source = WordSource::open(&[], &Some(path.to_path_buf()), 0).await?;
+    let mut count = 0;
+    while source.next_word().await?.is_some() {
+        count += 1;
+    }
+    Ok(count)
+}
+
+static META_REFRESH_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)<meta[^>]+http-equiv\s*=\s*["']?refresh["']?[^>]*content\s*=\s*["']?\s*\d*\s*;?\s*url\s*=\s*['"]?([^"'>\s]+)"#,
+    )
+    .unwrap()
+});
+
+static JS_REDIRECT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?:\blocation(?:\.href)?\s*=|\blocation\.(?:replace|assign)\s*\()\s*["']([^"']+)["']"#,
+    )
+    .unwrap()
+});
+
+/// Whether `url` is on the host of `base_url`, so recursion may scan it. In subdomain
+/// mode, every host of the domain after the keyword is in scope.
+fn in_scope(url: &url::Url, base_url: &url::Url, fuzz_mode: &FuzzMode, keyword: &str) -> bool {
+    let (Some(host), Some(base_host)) = (url.host_str(), base_url.host_str()) else {
+        return false;
+    };
+    if *fuzz_mode == FuzzMode::Subdomain
+        && let Some(position) = base_host.find(&keyword.to_ascii_lowercase())
+    {
+        let domain = &base_host[position + keyword.len()..];
+        return host.ends_with(domain) && host.len() > domain.len();
+    }
+    host == base_host
+}
+
+/// Finds the target of a `<meta http-equiv="refresh">` tag or a `window.location`
+/// assignment in an HTML body, resolved against the URL of the page.
+fn find_client_redirect(body: &str, page_url: &url::Url) -> Option<url::Url> {
+    let target = META_REFRESH_REGEX
+        .captures(body)
+        .or_else(|| JS_REDIRECT_REGEX.captures(body))?
+        .get(1)?
+        .as_str();
+    page_url.join(target).ok()
+}
+
+/// Counts the requests sent for one directory: the words after `skip_words` with the
+/// variants added by `extensions` and `extension_bypass`, combined with
+/// `extra_wordlists`.
+pub async fn count_scan_words(
+    words: &[String],
+    fuzz_mode: &FuzzMode,
+    options: &ScanConfig,
+) -> Result<usize> {
+    // Pitchfork pairs every main word with one word of each extra list, up to the end
+    // of the shortest list; otherwise every combination is requested.
+    let (limit, combinations) = if options.pitchfork {
+        let limit = options
+            .extra_wordlists
+            .iter()
+            .map(|list| list.len().saturating_sub(options.skip_words))
+            .min();
+        (limit, 1)
+    } else {
+        let combinations = options
+            .extra_wordlists
+            .iter()
+            .map(|list| list.len())
+            .fold(1, usize::saturating_mul);
+        (None, combinations)
+    };
+    let main_words = match &options.charset {
+        Some(charset) => Some(charset.count().saturating_sub(options.skip_words)),
+        None if options.wordlist_path.is_none() => {
+            Some(words.len().saturating_sub(options.skip_words))
+        }
+        None => None,
+    };
+    let count = match main_words {
+        // Without `extension_bypass`, every word has the same number of variants.
+        Some(main_words) if !options.extension_bypass => {
+            let variants = match fuzz_mode {
+                FuzzMode::Path => 1 + options.extensions.len(),
+                _ => 1,
+            };
+            limit
+                .map_or(main_words, |limit| main_words.min(limit))
+                .saturating_mul(variants)
+        }
+        _ => {
+            let mut source = WordSource::for_scan(words, options).await?;
+            let mut count = 0;
+            let mut counted_words = 0;
+            while limit.is_none_or(|limit| counted_words < limit)
+                && let Some(word) = source.next_word().await?
+            {
+                count += expand_word(word, fuzz_mode, options).len();
+                counted_words += 1;
+            }
+            count
+        }
+    };
+    Ok(count.saturating_mul(combinations))
+}
+
+/// Returns the words to request for the main word at position `index` of its list:
+/// the word combined with every combination of words of `extra_wordlists`, or with the
+/// words at the same position for `pitchfork`. Empty when a pitchfork list has no word
+/// at `index`.
+fn combine_words(word: String, index: usize, options: &ScanConfig) -> Vec<String> {
+    let mut combined = vec![word];
+    for list in &options.extra_wordlists {
+        let extras: &[String] = if options.pitchfork {
+            list.get(index..=index).unwrap_or_default()
+        } else {
+            list
+        };
+        combined = combined
+            .iter()
+            .flat_map(|prefix| {
+                extras
+                    .iter()
+                    .map(move |extra| format!("{}{}{}", prefix, PAYLOAD_SEPARATOR, extra))
+            })
+            .collect();
+    }
+    combined
+}
+
+/// Suffixes appended by `extension_bypass` to words with a file extension, to trick
+/// handlers that only look at the end of the path (`index.php%00.jpg`).
+const EXTENSION_BYPASS_SUFFIXES: [&str; 4] = ["%00.jpg", ";.jpg", ".", "%20"];
+
+/// Returns the words to request for one wordlist entry: the word itself and, for path
+/// fuzzing, the word with each of `extensions` appended (`admin.php`).
+fn expand_word(word: String, fuzz_mode: &FuzzMode, options: &ScanConfig) -> Vec<String> {
+    if *fuzz_mode != FuzzMode::Path {
+        return vec![word];
+    }
+    let with_extensions = options
+        .extensions
+        .iter()
+        .map(|extension| format!("{}.{}", word, extension));
+    std::iter::once(word.clone())
+        .chain(with_extensions)
+        .flat_map(|word| bypass_variants(word, options))
+        .collect()
+}
+
+/// Returns the word itself, followed by the extension bypass variants when
+/// `extension_bypass` is set and the word has an extension. The variants end up in the
+/// URL path verbatim (the `%00`/`%20` escapes are already URL-encoded).
+fn bypass_variants(word: String, options: &ScanConfig) -> Vec<String> {
+    let has_extension = word.rsplit_once('.').is_some_and(|(name, ext)| {
+        !name.is_empty()
+            && (1..=5).contains(&ext.len())
+            && ext.chars().all(|c| c.is_ascii_alphanumeric())
+    });
+    if !options.extension_bypass || !has_extension {
+        return vec![word];
+    }
+    let variants = EXTENSION_BYPASS_SUFFIXES
+        .iter()
+        .map(|suffix| format!("{}{}", word, suffix));
+    std::iter::once(word.clone()).chain(variants).collect()
+}
+
+/// Returns the position of `word` in a wordlist file, counting non-empty lines only.
+pub async fn word_position(path: &Path, word: &str) -> Result<Option<usize>> {
+    let mut source = WordSource::open(&[], &Some(path.to_path_buf()), 0).await?;
+    let mut index = 0;
+    while let Some(candidate) = source.next_word().await? {
+        if candidate == word {
+            return Ok(Some(index));
+        }
+        index += 1;
+    }
+    Ok(None)
+}
+
+/// The lines of a wordlist file, decompressed if it is gzipped.
+pub type WordlistLines = Lines<Box<dyn AsyncBufRead + Send + Unpin>>;
+
+/// Opens the wordlist at `path` for reading line by line. A wordlist with a `.gz`
+/// extension or starting with the gzip magic bytes is decompressed while it is read;
+/// concatenated members are joined, as `gunzip` does.
+pub async fn open_wordlist(path: &Path) -> std::io::Result<WordlistLines> {
+    let mut reader = BufReader::new(File::open(path).await?);
+    let gzip = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+        || reader.fill_buf().await?.starts_with(&[0x1f, 0x8b]);
+    let reader: Box<dyn AsyncBufRead + Send + Unpin> = if gzip {
+        let mut decoder = GzipDecoder::new(reader);
+        decoder.multiple_members(true);
+        Box::new(BufReader::new(decoder))
+    } else {
+        Box::new(reader)
+    };
+    Ok(reader.lines())
+}
+
+/// Yields the words for one directory scan, either from memory, streamed from disk or
+/// generated from a charset.
+enum WordSource<'a> {
+    Memory(std::slice::Iter<'a, String>),
+    File(WordlistLines),
+    Generated(std::iter::Skip<charset::Words>),
+}
+
+impl<'a> WordSource<'a> {
+    /// Opens the words of a scan per `options`, skipping `skip_words`.
+    async fn for_scan(words: &'a [String], options: &ScanConfig) -> Result<WordSource<'a>> {
+        match &options.charset {
+            Some(charset) => Ok(WordSource::Generated(charset.words().skip(options.skip_words))),
+            None => WordSource::open(words, &options.wordlist_path, options.skip_words).await,
+        }
+    }
+
+    /// Opens the word source, skipping its first `skip` words.
+    async fn open(
+        words: &'a [String],
+        path: &Option<PathBuf>,
+        skip: usize,
+    ) -> Result<WordSource<'a>> {
+        match path {
+            Some(path) => {
+                let mut source = WordSource::File(open_wordlist(path).await?);
+                for _ in 0..skip {
+                    if source.next_word().await?.is_none() {
+                        break;
+                    }
+                }
+                Ok(source)
+            }
+            None => Ok(WordSource::Memory(words.get(skip..).unwrap_or_default().iter())),
+        }
+    }
+
+    async fn next_word(&mut self) -> Result<Option<String>> {
+        match self {
+            WordSource::Memory(iter) => Ok(iter.next().cloned()),
+            WordSource::Generated(words) => Ok(words.next()),
+            WordSource::File(lines) => {
+                while let Some(line) = lines.next_line().await? {
+                    let word = line.trim();
+                    if !word.is_empty() {
+                        return Ok(Some(word.to_string()));
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Applies the status, count and regex filters of a scan to a response with the given
+/// status and counts. The match conditions are combined per `options.match_mode`; the
+/// exclusion filters always apply. Without a `body`, the regex filters are skipped. An
+/// unknown count (`None`) matches no count condition and is excluded by none.
+/// Used by [`perform_scan`] and to re-filter stored results.
+#[allow(clippy::too_many_arguments)]
+pub fn passes_filters(
+    status: u16,
+    words: Option<usize>,
+    chars: Option<usize>,
+    lines: Option<usize>,
+    body: Option<&str>,
+    headers: Option<&reqwest::header::HeaderMap>,
+    include_status: &Option<HashSet<u16>>,
+    exclude_status: &Option<HashSet<u16>>,
+    exact_words: &Option<Vec<usize>>,
+    exact_chars: &Option<Vec<usize>>,
+    exact_lines: &Option<Vec<usize>>,
+    exclude_exact_words: &Option<Vec<usize>>,
+    exclude_exact_chars: &Option<Vec<usize>>,
+    exclude_exact_lines: &Option<Vec<usize>>,
+    options: &ScanConfig,
+) -> bool {
+    // include_status takes precedence over exclude_status and is a match condition.
+    let status_reported = match (include_status, exclude_status) {
+        (Some(include), _) => include.contains(&status),
+        (None, Some(exclude)) => !exclude.contains(&status),
+        (None, None) => status != 404,
+    };
+    if include_status.is_none() && !status_reported {
+        return false;
+    }
+
+    let mut conditions = [
+        include_status.as_ref().map(|_| status_reported),
+        exact_words.as_ref().map(|list| words.is_some_and(|words| list.contains(&words))),
+        exact_chars.as_ref().map(|list| chars.is_some_and(|chars| list.contains(&chars))),
+        exact_lines.as_ref().map(|list| lines.is_some_and(|lines| list.contains(&lines))),
+        (!options.word_range.is_unbounded())
+            .then(|| words.is_some_and(|words| options.word_range.contains(words))),
+        (!options.char_range.is_unbounded())
+            .then(|| chars.is_some_and(|chars| options.char_range.contains(chars))),
+        (!options.line_range.is_unbounded())
+            .then(|| lines.is_some_and(|lines| options.line_range.contains(lines))),
+        body.zip(options.match_regex.as_ref()).map(|(body, regex)| regex.is_match(body)),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(headers.into_iter().flat_map(|headers| {
+        options.match_has_headers.iter().map(|name| headers.contains_key(name.as_str()))
+    }))
+    .peekable();
+    let matched = match options.match_mode {
+        MatchMode::All => conditions.all(|passed| passed),
+        MatchMode::Any => conditions.peek().is_none() || conditions.any(|passed| passed),
+    };
+
+    let excluded = [
+        (exclude_exact_words, words),
+        (exclude_exact_chars, chars),
+        (exclude_exact_lines, lines),
+    ]
+    .into_iter()
+    .any(|(list, count)| list.as_ref().zip(count).is_some_and(|(list, count)| list.contains(&count)))
+        || body
+            .zip(options.filter_regex.as_ref())
+            .is_some_and(|(body, regex)| regex.is_match(body))
+        || headers.is_some_and(|headers| {
+            options.filter_has_headers.iter().any(|name| headers.contains_key(name.as_str()))
+        });
+
+    matched && !excluded
+}
+
+/// Whether the media type of the `Content-Type` header, without parameters like the
+/// charset, starts with one of `match_content_types` (if any) and with none of
+/// `filter_content_types`. A response without the header matches no type.
+fn content_type_reported(headers: &reqwest::header::HeaderMap, options: &ScanConfig) -> bool {
+    let media_type = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|media_type| media_type.trim().to_ascii_lowercase());
+    let matches = |types: &[String]| {
+        media_type.as_ref().is_some_and(|media_type| {
+            types.iter().any(|prefix| media_type.starts_with(&prefix.to_ascii_lowercase()))
+        })
+    };
+    (options.match_content_types.is_empty() || matches(&options.match_content_types))
+        && !matches(&options.filter_content_types)
+}
+
+/// Whether the user headers (`Name: value`) contain the header `name`.
+pub fn has_header(headers: &[String], name: &str) -> bool {
+    headers.iter().any(|header| {
+        header
+            .split_once(':')
+            .is_some_and(|(header_name, _)| header_name.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+/// Sends a request with the client, or by hand with `raw_framing`. Network errors
+/// that `retry_classifier` accepts and 5xx responses are retried up to `retries` times,
+/// waiting `retry_backoff` milliseconds before the first retry and twice as long before
+/// each next one. Failures are reported as [`ScanEvent::ErrorOccurred`].
+///
+/// Returns the response with its time to first byte: the time from sending the last
+/// attempt until its status line and headers arrived. The body is streamed afterwards,
+/// and waiting for the rate limiter or a retry is not included.
+async fn send_request(
+    client: &Client,
+    mut request: reqwest::Request,
+    tx: &Sender<ScanEvent>,
+    options: &ScanConfig,
+) -> Result<(reqwest::Response, Duration)> {
+    let mut retries_left = options.retries;
+    let mut backoff = options.retry_backoff;
+    loop {
+        let retry = if retries_left > 0 { request.try_clone() } else { None };
+        if let Some(limiter) = &options.rate_limiter {
+            limiter.acquire().await;
+        }
+        let sent_at = std::time::Instant::now();
+        let result = match &options.raw_framing {
+            Some(framing) => framing.execute(&request).await,
+            None => client.execute(request).await.map_err(anyhow::Error::from),
+        };
+        let ttfb = sent_at.elapsed();
+        if let Some(next) = retry {
+            let transient = match &result {
+                Ok(res) => res.status().is_server_error(),
+                Err(e) => e
+                    .downcast_ref::<reqwest::Error>()
+                    .is_none_or(|e| options.retry_classifier.is_retryable(e)),
+            };
+            if transient {
+                retries_left -= 1;
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+                backoff = backoff.saturating_mul(2);
+                request = next;
+                continue;
+            }
+        }
+        if let Err(e) = &result {
+            let message = match e.downcast_ref::<reqwest::Error>() {
+                Some(e) => format!("{}: {}", classify_error(e), e),
+                None => format!("Raw request failed: {}", e),
+            };
+            tx.send(ScanEvent::ErrorOccurred(message)).await?;
+        }
+        return result.map(|res| (res, ttfb));
+    }
+}
+
+/// The `Cookie` header of a request to `url`: `cookies` (`name=value; ...`), followed by
+/// the cookies of `cookie_jar` and then the `given` cookies of the user headers that
+/// they do not override.
+async fn cookie_header(
+    url: &url::Url,
+    cookies: &str,
+    given: &str,
+    options: &ScanConfig,
+) -> Option<String> {
+    let stored = match &options.cookie_jar {
+        Some(jar) => jar.header_value(url).await,
+        None => None,
+    };
+    let name = |cookie: &str| {
+        cookie.split_once('=').map_or(cookie, |(name, _)| name).trim().to_string()
+    };
+    let mut all: Vec<&str> = cookies.split("; ").filter(|cookie| !cookie.is_empty()).collect();
+    let rest = stored.iter().flat_map(|stored| stored.split("; "));
+    for cookie in rest.chain(given.split(';').map(str::trim)).filter(|c| !c.is_empty()) {
+        if !all.iter().any(|c| name(c) == name(cookie)) {
+            all.push(cookie);
+        }
+    }
+    (!all.is_empty()).then(|| all.join("; "))
+}
+
+/// Follows up to `max_redirects` redirects, starting with `res`, the response to
+/// `template`, and its time to first byte. Each hop repeats the headers of `template`,
+/// but for its credentials on another origin; like browsers do, a 303 (and a POST
+/// answered with 301 or 302) continues with a GET without body. Returns the final URL,
+/// response and time to first byte, and the chain of hops, which is empty if `res` is
+/// no redirect. `cookies` are the fuzzed `cookies` of the scan and the cookies of the
+/// user headers, sent on every hop.
+#[allow(clippy::too_many_arguments)]
+async fn follow_redirects(
+    client: &Client,
+    template: reqwest::Request,
+    (mut res, mut ttfb): (reqwest::Response, Duration),
+    max_redirects: usize,
+    (cookies, given_cookies): (&str, &str),
+    tx: &Sender<ScanEvent>,
+    options: &ScanConfig,
+) -> Result<(url::Url, reqwest::Response, Duration, Vec<RedirectHop>)> {
+    let mut url = template.url().clone();
+    let mut method = template.method().clone();
+    let mut body = template.body().and_then(|body| body.as_bytes()).map(<[u8]>::to_vec);
+    let mut chain = Vec::new();
+    while res.status().is_redirection() && chain.len() < max_redirects {
+        let Some(next) = res
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| url.join(location).ok())
+        else {
+            break;
+        };
+        if let Some(jar) = &options.cookie_jar {
+            jar.store(&url, res.headers()).await;
+        }
+        let status = res.status().as_u16();
+        chain.push(RedirectHop {
+            url: url.to_string(),
+            status,
+        });
+        if (status == 303 && method != reqwest::Method::HEAD)
+            || (matches!(status, 301 | 302) && method == reqwest::Method::POST)
+        {
+            method = reqwest::Method::GET;
+            body = None;
+        }
+
+        let mut builder = client
+            .request(method.clone(), next.clone())
+            .headers(template.headers().clone());
+        if let Some(body) = &body {
+            builder = builder.body(body.clone());
+        }
+        let mut request = builder.build()?;
+        // A Host header given with -H names the original host; on another host, send
+        // the one of the target URL instead.
+        if next.host_str() != url.host_str()
+            || next.port_or_known_default() != url.port_or_known_default()
+        {
+            request.headers_mut().remove(reqwest::header::HOST);
+        }
+        // Credentials are meant for the scanned origin; keep them from other ones.
+        let cross_origin = next.origin() != template.url().origin();
+        if cross_origin {
+            for name in [
+                reqwest::header::AUTHORIZATION,
+                reqwest::header::COOKIE,
+                reqwest::header::PROXY_AUTHORIZATION,
+            ] {
+                request.headers_mut().remove(name);
+            }
+        }
+        if options.cookie_jar.is_some() {
+            request.headers_mut().remove(reqwest::header::COOKIE);
+            let (cookies, given_cookies) = if cross_origin { ("", "") } else { (cookies, given_cookies) };
+            if let Some(header) = cookie_header(&next, cookies, given_cookies, options).await {
+                request
+                    .headers_mut()
+                    .insert(reqwest::header::COOKIE, header.parse()?);
+            }
+        }
+        (res, ttfb) = send_request(client, request, tx, options).await?;
+        url = next;
+    }
+    if !chain.is_empty() {
+        chain.push(RedirectHop {
+            url: url.to_string(),
+            status: res.status().as_u16(),
+        });
+    }
+    Ok((url, res, ttfb, chain))
+}
+
+/// The error of a body read that took longer than `body_timeout`.
+#[derive(Debug)]
+struct SlowBody(Duration);
+
+impl std::fmt::Display for SlowBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the body was not received within {} ms", self.0.as_millis())
+    }
+}
+
+impl std::error::Error for SlowBody {}
+
+/// Reads the body up to `max_response_size`, failing with [`SlowBody`] after
+/// `body_timeout`.
+async fn read_body(res: reqwest::Response, options: &ScanConfig) -> Result<String> {
+    let read = read_body_limited(res, options.max_response_size);
+    match options.body_timeout {
+        Some(timeout) => tokio::time::timeout(timeout, read)
+            .await
+            .map_err(|_| SlowBody(timeout))?,
+        None => read.await,
+    }
+}
+
+/// Reads the response body as text, stopping after `limit` bytes if one is given.
+async fn read_body_limited(mut res: reqwest::Response, limit: Option<usize>) -> Result<String> {
+    let Some(limit) = limit else {
+        return Ok(res.text().await?);
+    };
+    let mut buf = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        let remaining = limit - buf.len();
+        if chunk.len() >= remaining {
+            buf.extend_from_slice(&chunk[..remaining]);
+            break;
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use httptest::responders;
@@ -442,7 +2757,79 @@ mod tests {
     use tokio::sync::{Mutex, Semaphore}; // Import Mutex and Semaphore
     use url::Url; // Explicit import
 
-    use crate::{HttpMethod, perform_scan, start_scan, ScanEvent}; // Import perform_scan and start_scan explicitly, and ScanEvent
+    use crate::{HttpMethod, perform_scan, start_scan, ScanEvent, ScanConfig}; // Import perform_scan and start_scan explicitly, and ScanEvent
+
+    /// The query pairs of the request built for `word` against `url` in parameter mode.
+    fn fuzzed_query(url: &str, word: &str) -> Vec<(String, String)> {
+        let (target_url, _) = crate::build_request(
+            &Client::new(),
+            &Url::parse(url).unwrap(),
+            word,
+            &HttpMethod::GET,
+            &crate::FuzzMode::Parameter,
+            &[],
+            &None,
+            &ScanConfig::default(),
+        )
+        .unwrap();
+        target_url
+            .query_pairs()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect()
+    }
+
+    fn fuzzed_url(url: &str, word: &str, fuzz_mode: crate::FuzzMode) -> String {
+        let options = ScanConfig {
+            extra_wordlists: vec![Arc::new(vec!["admin".to_string()])],
+            ..Default::default()
+        };
+        let (target_url, _) = crate::build_request(
+            &Client::new(),
+            &Url::parse(url).unwrap(),
+            word,
+            &HttpMethod::GET,
+            &fuzz_mode,
+            &[],
+            &None,
+            &options,
+        )
+        .unwrap();
+        target_url.to_string()
+    }
+
+    #[test]
+    fn test_keywords_in_host_and_path_take_their_own_words() {
+        let word = ["dev", "admin"].join(&crate::PAYLOAD_SEPARATOR.to_string());
+        assert_eq!(
+            fuzzed_url("http://FUZZ1.example.com/FUZZ2", &word, crate::FuzzMode::Subdomain),
+            "http://dev.example.com/admin"
+        );
+        assert_eq!(
+            fuzzed_url("http://example.com/api/FUZZ2/FUZZ1?x=1", &word, crate::FuzzMode::Path),
+            "http://example.com/api/admin/dev?x=1"
+        );
+        assert_eq!(
+            fuzzed_url("http://FUZZ.example.com/", "dev", crate::FuzzMode::Subdomain),
+            "http://dev.example.com/"
+        );
+    }
+
+    #[test]
+    fn test_parameter_fuzzing_replaces_every_keyword() {
+        let pair = |key: &str, value: &str| (key.to_string(), value.to_string());
+        assert_eq!(
+            fuzzed_query("http://example.com/?id=FUZZ&page=2", "7"),
+            [pair("id", "7"), pair("page", "2")]
+        );
+        assert_eq!(
+            fuzzed_query("http://example.com/?a=FUZZ&b=x-FUZZ", "7"),
+            [pair("a", "7"), pair("b", "x-7")]
+        );
+        assert_eq!(
+            fuzzed_query("http://example.com/?FUZZ=1&debug=FUZZ", "admin"),
+            [pair("admin", "1"), pair("debug", "admin")]
+        );
+    }
 
     #[tokio::test]
     async fn test_perform_scan_success() {
@@ -464,19 +2851,7 @@ mod tests {
             &base_url,
             "test_path",
             tx,
-            &HttpMethod::GET,
-            &None,
-            &None,
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // scan_delay
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            &crate::FuzzMode::Path,
-            &[],   // Add empty headers slice
-            &None, // Add data argument
+            &ScanConfig::default(),
         )
         .await;
         assert!(result.is_ok());
@@ -502,19 +2877,7 @@ mod tests {
             &base_url,
             "non_existent",
             tx,
-            &HttpMethod::GET,
-            &None,
-            &None,
-            None, // scan_delay
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            &crate::FuzzMode::Path,
-            &[],   // Add empty headers slice
-            &None, // Add data argument
+            &ScanConfig::default(),
         )
         .await;
         assert!(result.is_ok()); // 404 is a valid HTTP response, not an error in reqwest
@@ -547,19 +2910,7 @@ mod tests {
             &base_url,
             "timeout",
             tx,
-            &HttpMethod::GET,
-            &None,
-            &None,
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // scan_delay
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            &crate::FuzzMode::Path,
-            &[],   // Add empty headers slice
-            &None, // Add data argument
+            &ScanConfig::default(),
         )
         .await;
         assert!(result.is_err());
@@ -599,23 +2950,13 @@ mod tests {
             base_url.clone(),
             words,
             tx,
-            visited_urls.clone(),
-            test_rx_control, // Dummy receiver for control events
-            1, // Concurrency for testing
-            HttpMethod::GET,
-            None, // exclude_status
-            None, // include_status
-            max_depth,
-            None, // delay
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            crate::FuzzMode::Path,
-            vec![], // headers
-            None,   // data
+            test_rx_control,
+            ScanConfig {
+                visited_urls: visited_urls.clone(),
+                concurrency: 1,
+                max_depth,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -623,7 +2964,7 @@ mod tests {
         let mut received_found_urls = Vec::new();
         while let Some(msg) = rx.recv().await {
             if let ScanEvent::FoundUrl(s) = msg {
-                received_found_urls.push(s);
+                received_found_urls.push(s.to_string());
             }
         }
 
@@ -656,26 +2997,14 @@ mod tests {
             &base_url,
             "not_found",
             tx,
-            &HttpMethod::GET,
-            &None,
-            &None,
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // scan_delay
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            &crate::FuzzMode::Path,
-            &[],   // Add empty headers slice
-            &None, // Add data argument
+            &ScanConfig::default(),
         )
         .await;
         assert!(result.is_ok());
 
         // Ensure RequestCompleted is received, but no FoundUrl
         let first_msg = rx.recv().await.expect("Expected a message to be sent");
-        assert!(matches!(first_msg, ScanEvent::RequestCompleted));
+        assert!(matches!(first_msg, ScanEvent::RequestCompleted { .. }));
 
         tokio::time::sleep(Duration::from_millis(10)).await; // Give some time for any delayed messages
         assert!(rx.try_recv().is_err()); // Should be empty after consuming RequestCompleted
@@ -707,30 +3036,296 @@ mod tests {
         let result = perform_scan(
             &client,
             &base_url,
-            "testword", // This will replace FUZZ
+            "testword",
             tx,
-            &HttpMethod::POST,
-            &None,
-            &None,
-            None, // scan_delay
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            &crate::FuzzMode::Path, // FuzzMode doesn't directly apply to data fuzzing, but is required
-            &[],   // Add empty headers slice
-            &data_to_fuzz, // Pass the data to fuzz
+            &ScanConfig {
+                http_method: HttpMethod::POST,
+                data: data_to_fuzz.clone(),
+                ..Default::default()
+            },
         )
         .await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_detect_waf_from_headers() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        headers.insert("server", HeaderValue::from_static("nginx"));
+        assert_eq!(crate::detect_waf(&headers), None);
+        headers.insert("server", HeaderValue::from_static("AkamaiGHost"));
+        assert_eq!(crate::detect_waf(&headers), Some("Akamai"));
+        headers.insert("cf-ray", HeaderValue::from_static("8a1b2c3d4e5f-AMS"));
+        assert_eq!(crate::detect_waf(&headers), Some("Cloudflare"));
+    }
+
+    #[test]
+    fn test_find_client_redirect() {
+        let page = Url::parse("http://example.com/app/index.html").unwrap();
+        let meta = r#"<html><head><META HTTP-EQUIV="Refresh" CONTENT="0; URL='../hidden/'"></head></html>"#;
+        assert_eq!(
+            crate::find_client_redirect(meta, &page).unwrap().as_str(),
+            "http://example.com/hidden/"
+        );
+        let js = r#"<script>window.location.href = "/login?next=app";</script>"#;
+        assert_eq!(
+            crate::find_client_redirect(js, &page).unwrap().as_str(),
+            "http://example.com/login?next=app"
+        );
+        let js = r#"<script>location.replace('https://other.example/');</script>"#;
+        assert_eq!(
+            crate::find_client_redirect(js, &page).unwrap().as_str(),
+            "https://other.example/"
+        );
+        assert_eq!(crate::find_client_redirect("<p>no redirect</p>", &page), None);
+    }
+
+    #[test]
+    fn test_extract_urls_from_result_line() {
+        let line = "[301] http://example.com/admin -> http://example.com/admin/ [0W, 0C, 0L]";
+        let urls: Vec<_> = crate::extract_urls(line).collect();
+        assert_eq!(urls, ["http://example.com/admin", "http://example.com/admin/"]);
+        assert_eq!(crate::extract_urls("Warning: no url here").next(), None);
+    }
+
+    #[test]
+    fn test_in_scope_keeps_recursion_on_the_scanned_host() {
+        let url = |url: &str| Url::parse(url).unwrap();
+        let base = url("http://example.com/app/");
+        for (found, expected) in [
+            ("http://example.com/app/admin/", true),
+            ("https://example.com/login/", true),
+            ("http://cdn.example.com/app/", false),
+            ("http://evil.test/", false),
+        ] {
+            assert_eq!(
+                crate::in_scope(&url(found), &base, &crate::FuzzMode::Path, "FUZZ"),
+                expected,
+                "{}",
+                found
+            );
+        }
+
+        let base = url("http://FUZZ.example.com/");
+        for (found, expected) in [
+            ("http://admin.example.com/", true),
+            ("http://a.b.example.com/", true),
+            ("http://example.com/", false),
+            ("http://admin.example.org/", false),
+        ] {
+            assert_eq!(
+                crate::in_scope(&url(found), &base, &crate::FuzzMode::Subdomain, "FUZZ"),
+                expected,
+                "{}",
+                found
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_perform_scan_header_jitter_varies_order() {
+        use tokio::io::AsyncReadExt;
+
+        // Answers every request and reports the order of its X-Test-* headers.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (order_tx, mut order_rx) = mpsc::channel(100);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                let order: Vec<String> = String::from_utf8_lossy(&buf[..n])
+                    .lines()
+                    .filter(|line| line.starts_with("x-test-"))
+                    .map(str::to_string)
+                    .collect();
+                order_tx.send(order).await.unwrap();
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let client = Client::builder().http1_only().build().unwrap();
+        let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        let headers: Vec<String> = ["X-Test-A: 1", "X-Test-B: 2", "X-Test-C: 3"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+        let options = ScanConfig {
+            headers,
+            header_jitter: true,
+            ..Default::default()
+        };
+        let mut orders = HashSet::new();
+        for _ in 0..20 {
+            let (tx, _rx) = mpsc::channel(100);
+            perform_scan(&client, &base_url, "word", tx, &options)
+            .await
+            .unwrap();
+            let order = order_rx.recv().await.unwrap();
+            assert_eq!(order.len(), 3);
+            orders.insert(order);
+        }
+        assert!(orders.len() > 1, "Header order never changed: {:?}", orders);
+    }
+
+    #[tokio::test]
+    async fn test_perform_scan_does_not_read_redirect_body() {
+        use tokio::io::AsyncReadExt;
+
+        // The redirect announces a large body but never sends it: reading it would time out.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            let _ = socket
+                .write_all(b"HTTP/1.1 301 Moved Permanently\r\nLocation: /new/\r\nContent-Length: 104857600\r\n\r\n")
+                .await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(1))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+
+        let result = perform_scan(
+            &client,
+            &base_url,
+            "old",
+            tx,
+            &ScanConfig::default(),
+        )
+        .await;
+
+        assert!(result.unwrap().is_some());
+        assert!(matches!(rx.recv().await, Some(ScanEvent::RequestCompleted { .. })));
+        match rx.recv().await {
+            Some(ScanEvent::FoundUrl(line)) => assert!(line
+                .to_string()
+                .ends_with(&format!("old -> http://{}/new/ [0W, 0C, 0L]", addr))),
+            other => panic!("Expected FoundUrl, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_perform_scan_aborts_trickled_body_after_body_timeout() {
+        use tokio::io::AsyncReadExt;
+
+        // Sends the headers at once, then one byte of the body every 200 ms.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 50\r\n\r\n").await;
+            for _ in 0..50 {
+                if socket.write_all(b"x").await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+        });
+
+        let client = Client::builder().timeout(Duration::from_secs(10)).build().unwrap();
+        let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+        let started = std::time::Instant::now();
+
+        let result = perform_scan(
+            &client,
+            &base_url,
+            "slow",
+            tx,
+            &ScanConfig {
+                body_timeout: Some(Duration::from_millis(500)),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(rx.recv().await, Some(ScanEvent::RequestCompleted { .. })));
+        match rx.recv().await {
+            Some(ScanEvent::ErrorOccurred(msg)) => {
+                assert!(msg.starts_with("slow response body: "), "{}", msg)
+            }
+            other => panic!("Expected ErrorOccurred, got {:?}", other),
+        }
+    }
+
+    /// Serves HTTP/2 on a local port and fails the first request: either the stream is
+    /// reset, or (with `go_away`) the connection is closed with a GOAWAY frame that does
+    /// not cover the request.
+    async fn spawn_failing_h2_server(go_away: bool) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut connection = h2::server::handshake(socket).await.unwrap();
+            if go_away {
+                // Give the request time to arrive, then refuse it with the connection.
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                connection.abrupt_shutdown(h2::Reason::ENHANCE_YOUR_CALM);
+            } else if let Some(Ok((_request, mut respond))) = connection.accept().await {
+                respond.send_reset(h2::Reason::INTERNAL_ERROR);
+            }
+            // Keep driving the connection so the frames are flushed.
+            while connection.accept().await.is_some() {}
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_perform_scan_classifies_http2_errors() {
+        for (go_away, expected) in [
+            (false, crate::RequestErrorKind::StreamReset),
+            (true, crate::RequestErrorKind::GoAway),
+        ] {
+            let addr = spawn_failing_h2_server(go_away).await;
+            let client = Client::builder()
+                .timeout(Duration::from_secs(1))
+                .http2_prior_knowledge()
+                .build()
+                .unwrap();
+            let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+            let (tx, mut rx) = mpsc::channel(100);
+
+            let result = perform_scan(
+                &client,
+                &base_url,
+                "reset",
+                tx,
+                &ScanConfig::default(),
+            )
+            .await;
+
+            let err = result.unwrap_err();
+            let reqwest_err = err.downcast_ref::<reqwest::Error>().unwrap();
+            assert_eq!(crate::classify_error(reqwest_err), expected);
+            match rx.recv().await {
+                Some(ScanEvent::ErrorOccurred(msg)) => {
+                    assert!(msg.starts_with(&expected.to_string()), "{}", msg)
+                }
+                other => panic!("Expected ErrorOccurred, got {:?}", other),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod start_scan_tests {
-    use crate::{HttpMethod, start_scan}; // Import start_scan explicitly
+    use crate::{start_scan, ScanConfig}; // Import start_scan explicitly
     use crate::ScanEvent;
     use httptest::responders;
     use httptest::{Expectation, Server, matchers::*};
@@ -781,23 +3376,13 @@ mod start_scan_tests {
             base_url,
             words,
             tx,
-            visited_urls.clone(), // Added visited_urls argument
-            test_rx_control, // Dummy receiver for control events
-            1, // Concurrency for testing
-            HttpMethod::GET,
-            None, // exclude_status
-            None, // include_status
-            1,    // max_depth = 1 (no recursion)
-            None, // delay
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            crate::FuzzMode::Path,
-            vec![], // Add empty headers vector
-            None,   // data
+            test_rx_control,
+            ScanConfig {
+                visited_urls: visited_urls.clone(),
+                concurrency: 1,
+                max_depth: 1,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -808,13 +3393,13 @@ mod start_scan_tests {
         }
 
         assert!(
-            received_messages.iter().any(|e| matches!(e, ScanEvent::FoundUrl(s) if s == &format!("[200 OK] {} [0W, 0C, 0L]", server.url("/admin/"))))
+            received_messages.iter().any(|e| matches!(e, ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] {} [0W, 0C, 0L]", server.url("/admin/"))))
         );
         assert!(
-            received_messages.iter().any(|e| matches!(e, ScanEvent::FoundUrl(s) if s == &format!("[200 OK] {} [0W, 0C, 0L]", server.url("/test"))))
+            received_messages.iter().any(|e| matches!(e, ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] {} [0W, 0C, 0L]", server.url("/test"))))
         );
         // Should not contain /admin/users as recursion depth is 1
-        assert!(!received_messages.iter().any(|e| matches!(e, ScanEvent::FoundUrl(s) if s == &format!("[200 OK] {}", server.url("/admin/users")))));
+        assert!(!received_messages.iter().any(|e| matches!(e, ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] {}", server.url("/admin/users")))));
     }
 
     #[tokio::test]
@@ -848,26 +3433,16 @@ mod start_scan_tests {
 
         start_scan(
             client,
-            base_url.clone(), // Clone base_url here
+            base_url.clone(),
             words,
             tx,
-            visited_urls.clone(),
-            test_rx_control, // Dummy receiver for control events
-            1, // Concurrency for testing
-            HttpMethod::GET,
-            None, // exclude_status
-            None, // include_status
-            max_depth,
-            None, // delay
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            crate::FuzzMode::Path,
-            vec![], // Add empty headers vector
-            None,   // data
+            test_rx_control,
+            ScanConfig {
+                visited_urls: visited_urls.clone(),
+                concurrency: 1,
+                max_depth,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -876,7 +3451,7 @@ mod start_scan_tests {
         // Consume all events until ScanFinished
         while let Some(msg) = rx.recv().await {
             if let ScanEvent::FoundUrl(s) = msg {
-                received_found_urls.push(s);
+                received_found_urls.push(s.to_string());
             }
         }
 
@@ -913,4 +3488,280 @@ mod start_scan_tests {
             "Should not receive further messages after scan completion"
         );
     }
+
+    #[tokio::test]
+    async fn test_start_scan_replay_failed_recovers() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // The first connection is dropped without a response, the second one succeeds.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            drop(first);
+            let (mut second, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = second.read(&mut buf).await;
+            let _ = second
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+        });
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(1))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+        let (_test_tx_control, test_rx_control) = tokio::sync::broadcast::channel(1);
+
+        start_scan(
+            client,
+            base_url,
+            vec!["flaky".to_string()],
+            tx,
+            test_rx_control,
+            ScanConfig {
+                concurrency: 1,
+                max_depth: 1,
+                replay_failed: true,
+                replay_backoff: 0,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut received_messages = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            received_messages.push(msg);
+        }
+
+        assert!(received_messages.iter().any(|e| matches!(e, ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] http://{}/flaky [0W, 0C, 0L]", addr))));
+        assert!(received_messages.contains(&ScanEvent::ReplayFinished {
+            replayed: 1,
+            recovered: 1
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_start_scan_replay_failed_counts_only_replayed_words() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // The first connection is dropped without a response, all later ones succeed.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            drop(first);
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(1))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+        let (_test_tx_control, test_rx_control) = tokio::sync::broadcast::channel(1);
+
+        // The replayed directory is recursed into; its words are not replayed words.
+        start_scan(
+            client,
+            base_url,
+            vec!["dir/".to_string()],
+            tx,
+            test_rx_control,
+            ScanConfig {
+                concurrency: 1,
+                max_depth: 2,
+                replay_failed: true,
+                replay_backoff: 0,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut received_messages = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            received_messages.push(msg);
+        }
+
+        assert!(received_messages.iter().any(|e| matches!(e, ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] http://{}/dir/dir/ [0W, 0C, 0L]", addr))));
+        assert!(received_messages.contains(&ScanEvent::ReplayFinished {
+            replayed: 1,
+            recovered: 1
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_start_scan_replay_failed_uses_retry_classifier() {
+        // The only connection is dropped without a response.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            drop(first);
+        });
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(1))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+        let (_test_tx_control, test_rx_control) = tokio::sync::broadcast::channel(1);
+        let classified = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let classified_clone = classified.clone();
+
+        start_scan(
+            client,
+            base_url,
+            vec!["flaky".to_string()],
+            tx,
+            test_rx_control,
+            ScanConfig {
+                concurrency: 1,
+                max_depth: 1,
+                replay_failed: true,
+                replay_backoff: 0,
+                retry_classifier: crate::RetryClassifier::new(move |_| {
+                    classified_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    false
+                }),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut received_messages = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            received_messages.push(msg);
+        }
+
+        assert_eq!(classified.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(
+            !received_messages
+                .iter()
+                .any(|e| matches!(e, ScanEvent::ReplayFinished { .. })),
+            "{:?}",
+            received_messages
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_scan_survives_a_panicking_task() {
+        // Every connection is dropped without a response.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                drop(socket);
+            }
+        });
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(1))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+        let (_test_tx_control, test_rx_control) = tokio::sync::broadcast::channel(1);
+
+        start_scan(
+            client,
+            base_url,
+            vec!["boom1".to_string(), "boom2".to_string()],
+            tx,
+            test_rx_control,
+            ScanConfig {
+                concurrency: 1,
+                max_depth: 1,
+                replay_failed: true,
+                replay_backoff: 0,
+                retry_classifier: crate::RetryClassifier::new(|_| panic!("classifier bug")),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut received_messages = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            received_messages.push(msg);
+        }
+
+        let panics = received_messages
+            .iter()
+            .filter(|e| {
+                matches!(e, ScanEvent::ErrorOccurred(msg)
+                    if msg == "A scan task panicked: classifier bug")
+            })
+            .count();
+        assert_eq!(panics, 2, "{:?}", received_messages);
+        assert!(matches!(received_messages.last(), Some(ScanEvent::ScanFinished)));
+    }
+
+    #[tokio::test]
+    async fn test_start_scan_idle_timeout_aborts_stalled_requests() {
+        // Accept connections but never respond.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                connections.push(socket);
+            }
+        });
+
+        let client = Client::builder().build().unwrap();
+        let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+        let (_test_tx_control, test_rx_control) = tokio::sync::broadcast::channel(1);
+
+        let scan = start_scan(
+            client,
+            base_url,
+            vec!["hang1".to_string(), "hang2".to_string(), "hang3".to_string()],
+            tx,
+            test_rx_control,
+            ScanConfig {
+                concurrency: 2,
+                max_depth: 1,
+                idle_timeout: Some(1),
+                ..Default::default()
+            },
+        );
+        tokio::time::timeout(Duration::from_secs(10), scan)
+            .await
+            .expect("Scan should not hang")
+            .unwrap();
+
+        let mut warnings = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            if let ScanEvent::Warning(warning) = msg {
+                warnings.push(warning);
+            }
+        }
+        // Two requests stall with the permits, then the third one.
+        assert_eq!(
+            warnings,
+            vec![
+                "No request completed for 1 s; aborted 2 stalled requests.".to_string(),
+                "No request completed for 1 s; aborted 1 stalled requests.".to_string(),
+            ]
+        );
+    }
 }
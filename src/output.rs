@@ -0,0 +1,449 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use dirnutek::{Classification, Finding};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+/// The key used by `--sort-output` to order the results of a scan.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+pub enum SortBy {
+    /// Order by URL (base URL, then path), then by status code.
+    #[default]
+    Url,
+    /// Order by status code, then by URL.
+    Status,
+    /// Order by response size in characters, then by URL.
+    Size,
+}
+
+/// How the console reports results.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// One line per result, e.g. `[200 OK] http://example.com/admin [1W, 5C, 1L]`.
+    #[default]
+    Plain,
+    /// One compact JSON object per result and line (NDJSON), printed as soon as it is
+    /// found, so the output can be followed with `tail -f` or piped into `jq`.
+    #[value(alias = "ndjson")]
+    Json,
+    /// Comma-separated values with the [`CSV_HEADER`] row, for spreadsheets.
+    Csv,
+    /// Only the URL of each result (`--urls-only`), to pipe into other tools.
+    Urls,
+    /// Like `Urls`, followed by the redirect target on its own line
+    /// (`--include-redirect-urls`).
+    #[value(skip)]
+    UrlsAndRedirects,
+}
+
+/// Renders a finding in `format`. With `show_protected`, protected plain results are
+/// marked with `[protected]`; with `show_time`, plain results include the time to
+/// first byte.
+pub fn format_finding(
+    finding: &Finding,
+    format: OutputFormat,
+    show_protected: bool,
+    show_time: bool,
+) -> serde_json::Result<String> {
+    let line = if show_time {
+        format!("{:#}", finding)
+    } else {
+        finding.to_string()
+    };
+    Ok(match format {
+        OutputFormat::Plain
+            if show_protected && finding.classification == Classification::Protected =>
+        {
+            format!("{} [protected]", line)
+        }
+        OutputFormat::Plain => line,
+        OutputFormat::Json => serde_json::to_string(finding)?,
+        OutputFormat::Csv => csv_row(finding),
+        OutputFormat::Urls => finding.url.clone(),
+        OutputFormat::UrlsAndRedirects => match &finding.redirect_target {
+            Some(target) => format!("{}\n{}", finding.url, target),
+            None => finding.url.clone(),
+        },
+    })
+}
+
+/// The last line of a completed scan with `--output-format json`, also when nothing
+/// was found: `{"event":"completed","hits":0}`.
+pub fn completion_record(hits: usize) -> String {
+    serde_json::json!({ "event": "completed", "hits": hits }).to_string()
+}
+
+/// A plain result line: status, URL, an optional redirect target and the counts, with
+/// the response time of `--show-time`.
+static PLAIN_FINDING_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\[(\d{3})[^\]]*\] (\S+)(?: -> (.+?))? \[(\d+|\?)W, (\d+|\?)C, (\d+|\?)L(?:, \d+ms)?\]")
+        .expect("valid regex")
+});
+
+/// Parses a plain result line like `[301 Moved Permanently] http://example.com/docs ->
+/// /docs/ [0W, 0C, 0L]` back into a finding. Followed redirect chains are kept as the
+/// redirect target; request IDs and words are dropped.
+pub fn parse_finding(line: &str) -> Option<Finding> {
+    let captures = PLAIN_FINDING_REGEX.captures(line)?;
+    // `?` is an unknown count.
+    let count = |i: usize| match &captures[i] {
+        "?" => Some(None),
+        count => count.parse::<usize>().ok().map(Some),
+    };
+    let status = captures[1].parse().ok()?;
+    Some(Finding {
+        url: captures[2].to_string(),
+        status,
+        redirect_target: captures
+            .get(3)
+            .map(|target| target.as_str().trim_end_matches(" (client redirect)").to_string()),
+        words: count(4)?,
+        chars: count(5)?,
+        lines: count(6)?,
+        classification: Classification::from_status(status),
+        ..Default::default()
+    })
+}
+
+/// The header row of `--output-format csv`.
+pub const CSV_HEADER: &str = "url,status,redirect,words,chars,lines";
+
+/// Renders a finding as a CSV row matching [`CSV_HEADER`].
+pub fn csv_row(finding: &Finding) -> String {
+    [
+        csv_field(&finding.url),
+        finding.status.to_string(),
+        csv_field(finding.redirect_target.as_deref().unwrap_or_default()),
+        finding.words.map(|n| n.to_string()).unwrap_or_default(),
+        finding.chars.map(|n| n.to_string()).unwrap_or_default(),
+        finding.lines.map(|n| n.to_string()).unwrap_or_default(),
+    ]
+    .join(",")
+}
+
+/// Parses a row written by [`csv_row`] back into a finding. The header row and rows
+/// with another number of fields yield `None`. Empty counts are unknown.
+pub fn parse_csv_row(line: &str) -> Option<Finding> {
+    let fields = split_csv(line)?;
+    let [url, status, redirect, words, chars, lines] = fields.as_slice() else {
+        return None;
+    };
+    let status = status.parse().ok()?;
+    let count = |count: &String| match count.as_str() {
+        "" => Some(None),
+        count => count.parse::<usize>().ok().map(Some),
+    };
+    Some(Finding {
+        url: url.clone(),
+        status,
+        redirect_target: (!redirect.is_empty()).then(|| redirect.clone()),
+        words: count(words)?,
+        chars: count(chars)?,
+        lines: count(lines)?,
+        classification: Classification::from_status(status),
+        ..Default::default()
+    })
+}
+
+/// Splits a CSV line into its fields, unquoting the fields quoted by [`csv_field`].
+fn split_csv(line: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+/// Quotes a CSV field as per RFC 4180 if it contains a comma, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A reported result line together with the fields it is sorted by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScanResult {
+    pub line: String,
+    pub url: String,
+    pub status: u16,
+    /// The number of characters of the body, `None` if it is unknown.
+    pub chars: Option<usize>,
+}
+
+impl ScanResult {
+    /// A result rendered as `line` from `finding`.
+    pub fn new(line: String, finding: &Finding) -> Self {
+        Self {
+            line,
+            url: finding.url.clone(),
+            status: finding.status,
+            chars: finding.chars,
+        }
+    }
+
+    /// Parses a line in the `[200 OK] http://example.com/admin [5W, 20C, 1L]` format.
+    /// Fields that cannot be parsed fall back to empty/zero/unknown, so such lines sort
+    /// first.
+    pub fn parse(line: String) -> Self {
+        let url = dirnutek::extract_urls(&line).next().unwrap_or_default().to_string();
+        let status = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.get(..3))
+            .and_then(|code| code.parse().ok())
+            .unwrap_or_default();
+        let chars = line
+            .split(" [")
+            .find(|part| part.ends_with("L]"))
+            .and_then(|counts| counts.split(", ").nth(1))
+            .and_then(|c| c.strip_suffix('C'))
+            .and_then(|c| c.parse().ok());
+        Self {
+            line,
+            url,
+            status,
+            chars,
+        }
+    }
+
+    /// Compares two results by `sort_by`, falling back to URL and status.
+    pub fn compare(&self, other: &Self, sort_by: SortBy) -> Ordering {
+        let by_url = self.url.cmp(&other.url).then(self.status.cmp(&other.status));
+        match sort_by {
+            SortBy::Url => by_url,
+            SortBy::Status => self.status.cmp(&other.status).then(by_url),
+            SortBy::Size => self.chars.cmp(&other.chars).then(by_url),
+        }
+    }
+}
+
+/// Sorts buffered results in place.
+pub fn sort_results(results: &mut [ScanResult], sort_by: SortBy) {
+    results.sort_by(|a, b| a.compare(b, sort_by));
+}
+
+/// A node of the tree rendered by [`render_tree`], with its children by name.
+#[derive(Default)]
+struct TreeNode(BTreeMap<String, TreeNode>);
+
+/// Renders URLs as an indented directory tree per origin, like `tree`. Paths are split
+/// on `/`, so `/a` and `/a/` end up in the same node; a query string stays part of the
+/// last segment.
+pub fn render_tree(urls: &[String]) -> String {
+    let mut roots: BTreeMap<String, TreeNode> = BTreeMap::new();
+    for url in urls {
+        let Ok(url) = url::Url::parse(url) else {
+            continue;
+        };
+        let origin = url[..url::Position::BeforePath].to_string();
+        let mut path = url[url::Position::BeforePath..url::Position::AfterQuery].to_string();
+        if let Some(query_start) = path.find('?') {
+            // Keep the query with the segment before it: `/page/?id=1` -> `page?id=1`.
+            let query = path.split_off(query_start);
+            path = path.trim_end_matches('/').to_string() + &query;
+        }
+        let mut node = roots.entry(origin).or_default();
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            node = node.0.entry(segment.to_string()).or_default();
+        }
+    }
+
+    let mut out = String::new();
+    for (origin, node) in &roots {
+        out.push_str(origin);
+        out.push_str("/\n");
+        render_children(node, "", &mut out);
+    }
+    out
+}
+
+fn render_children(node: &TreeNode, prefix: &str, out: &mut String) {
+    for (i, (name, child)) in node.0.iter().enumerate() {
+        let last = i + 1 == node.0.len();
+        out.push_str(prefix);
+        out.push_str(if last { "└── " } else { "├── " });
+        out.push_str(name);
+        out.push('\n');
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        render_children(child, &child_prefix, out);
+    }
+}
+
+/// The directory containing `url` for `--one-per-dir`: the URL up to and including the
+/// last `/` before the final path segment, without query. `http://h/admin/` and
+/// `http://h/admin.php` are both in `http://h/`; `http://h/admin/x` is in `http://h/admin/`.
+pub fn parent_directory(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let path = url.path().trim_end_matches('/');
+    let parent = path.rfind('/').map_or("/", |slash| &path[..=slash]);
+    Some(format!("{}{}", &url[..url::Position::BeforePath], parent))
+}
+
+/// The `--output-per-host` file name for the host of `url`, e.g. `example.com_8080.json`
+/// for `http://example.com:8080/admin` in JSON format. Characters other than ASCII
+/// letters, digits, `.` and `-` are replaced with `_`, so the name is safe on every
+/// file system and cannot leave the output directory.
+pub fn host_file_name(url: &str, format: OutputFormat) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let host = match url.port() {
+        Some(port) => format!("{}_{}", url.host_str()?, port),
+        None => url.host_str()?.to_string(),
+    };
+    let mut name: String = host
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    if name.starts_with('.') {
+        name.insert(0, '_');
+    }
+    let extension = match format {
+        OutputFormat::Json => "json",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Plain | OutputFormat::Urls | OutputFormat::UrlsAndRedirects => "txt",
+    };
+    Some(format!("{}.{}", name, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutputFormat, completion_record, csv_row, format_finding, host_file_name, parent_directory, parse_csv_row, parse_finding, render_tree};
+    use dirnutek::Finding;
+
+    #[test]
+    fn test_parse_finding_round_trips_plain_lines() {
+        let finding = Finding {
+            url: "http://example.com/docs".to_string(),
+            status: 301,
+            redirect_target: Some("/docs/".to_string()),
+            ..Default::default()
+        };
+        let parsed = parse_finding(&finding.to_string()).unwrap();
+        assert_eq!((parsed.url, parsed.status), (finding.url, 301));
+        assert_eq!(parsed.redirect_target.as_deref(), Some("/docs/"));
+        assert_eq!(
+            parse_finding("[200 OK] http://example.com/admin [5W, 20C, 1L] [word: admin]")
+                .map(|f| (f.words, f.chars, f.lines)),
+            Some((Some(5), Some(20), Some(1)))
+        );
+        assert!(parse_finding("# Scan finished.").is_none());
+    }
+
+    #[test]
+    fn test_show_time_adds_the_time_to_first_byte() {
+        let finding = Finding {
+            url: "http://example.com/admin".to_string(),
+            status: 200,
+            words: Some(1),
+            chars: Some(0),
+            lines: Some(0),
+            ttfb_ms: 12,
+            ..Default::default()
+        };
+        let line = format_finding(&finding, OutputFormat::Plain, false, true).unwrap();
+        assert_eq!(line, "[200 OK] http://example.com/admin [1W, 0C, 0L, 12ms]");
+        assert_eq!(parse_finding(&line).map(|f| f.words), Some(Some(1)));
+        let line = format_finding(&finding, OutputFormat::Plain, false, false).unwrap();
+        assert_eq!(line, "[200 OK] http://example.com/admin [1W, 0C, 0L]");
+    }
+
+    #[test]
+    fn test_csv_row_quotes_special_fields() {
+        let finding = Finding {
+            url: "http://example.com/a,b".to_string(),
+            status: 301,
+            redirect_target: Some("/say \"hi\"".to_string()),
+            words: Some(1),
+            chars: Some(2),
+            lines: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            csv_row(&finding),
+            "\"http://example.com/a,b\",301,\"/say \"\"hi\"\"\",1,2,3"
+        );
+        let parsed = parse_csv_row(&csv_row(&finding)).unwrap();
+        assert_eq!(parsed.url, finding.url);
+        assert_eq!(parsed.redirect_target, finding.redirect_target);
+        assert_eq!(
+            (parsed.status, parsed.words, parsed.chars, parsed.lines),
+            (301, Some(1), Some(2), Some(3))
+        );
+        assert!(parse_csv_row(super::CSV_HEADER).is_none());
+    }
+
+    #[test]
+    fn test_render_tree_merges_trailing_slashes() {
+        let urls = [
+            "http://example.com/admin",
+            "http://example.com/admin/",
+            "http://example.com/admin/login.php",
+            "http://example.com/backup/old/",
+            "http://example.com/page?id=1",
+            "http://other.example:8080/api",
+        ]
+        .map(String::from);
+        assert_eq!(
+            render_tree(&urls),
+            "http://example.com/\n\
+             ├── admin\n\
+             │   └── login.php\n\
+             ├── backup\n\
+             │   └── old\n\
+             └── page?id=1\n\
+             http://other.example:8080/\n\
+             └── api\n"
+        );
+    }
+
+    #[test]
+    fn test_parent_directory_ignores_trailing_slash_and_query() {
+        assert_eq!(parent_directory("http://h/admin/").as_deref(), Some("http://h/"));
+        assert_eq!(parent_directory("http://h/admin.php?id=1").as_deref(), Some("http://h/"));
+        assert_eq!(parent_directory("http://h:8080/admin/x/").as_deref(), Some("http://h:8080/admin/"));
+        assert_eq!(parent_directory("http://h/").as_deref(), Some("http://h/"));
+        assert_eq!(parent_directory("not a url"), None);
+    }
+
+    #[test]
+    fn test_host_file_name_is_sanitized() {
+        let name = |url| host_file_name(url, OutputFormat::Plain);
+        assert_eq!(name("http://example.com/admin").as_deref(), Some("example.com.txt"));
+        assert_eq!(
+            host_file_name("https://example.com:8443/", OutputFormat::Json).as_deref(),
+            Some("example.com_8443.json")
+        );
+        assert_eq!(
+            host_file_name("http://10.0.0.1/", OutputFormat::Csv).as_deref(),
+            Some("10.0.0.1.csv")
+        );
+        assert_eq!(name("http://[::1]:8080/").as_deref(), Some("___1__8080.txt"));
+        assert_eq!(name("not a url"), None);
+    }
+
+    #[test]
+    fn test_completion_record_reports_zero_hits() {
+        let record: serde_json::Value = serde_json::from_str(&completion_record(0)).unwrap();
+        assert_eq!(record, serde_json::json!({ "event": "completed", "hits": 0 }));
+    }
+}
@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::header::{CONNECTION, CONTENT_LENGTH, HOST, TRANSFER_ENCODING};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// The most bytes read before the end of the response headers.
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// Body framing overrides for `--raw-framing`, for probing request smuggling (desync)
+/// issues. Requests are sent over a plain HTTP/1.1 connection written by hand, since
+/// reqwest always frames the body itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawFraming {
+    /// Send this `Content-Length` whatever the size of the body.
+    pub content_length: Option<u64>,
+    /// Send `Transfer-Encoding: chunked` with the body as a single chunk.
+    pub chunked: bool,
+    /// How long a raw request may take, like the timeout of the regular client.
+    pub timeout: Option<Duration>,
+    /// How long connecting may take.
+    pub connect_timeout: Option<Duration>,
+    /// Stop reading the response after this many body bytes, like `max_response_size`
+    /// does for the regular client.
+    pub max_response_size: Option<usize>,
+}
+
+impl RawFraming {
+    /// Serializes `request` as HTTP/1.1 with this framing. The connection is closed
+    /// after the response, so the response can be read to the end.
+    fn encode(&self, request: &reqwest::Request) -> Vec<u8> {
+        let url = request.url();
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\n",
+            request.method(),
+            &url[url::Position::BeforePath..url::Position::AfterQuery]
+        );
+        let host = &url[url::Position::BeforeHost..url::Position::AfterPort];
+        head.push_str(&format!("Host: {}\r\n", host));
+        for (name, value) in request.headers() {
+            if [HOST, CONNECTION, CONTENT_LENGTH, TRANSFER_ENCODING].contains(name) {
+                continue;
+            }
+            head.push_str(&format!("{}: {}\r\n", name, String::from_utf8_lossy(value.as_bytes())));
+        }
+        head.push_str("Connection: close\r\n");
+
+        let body = request.body().and_then(|body| body.as_bytes()).unwrap_or_default();
+        match self.content_length {
+            Some(length) => head.push_str(&format!("Content-Length: {}\r\n", length)),
+            None if !self.chunked && !body.is_empty() => {
+                head.push_str(&format!("Content-Length: {}\r\n", body.len()))
+            }
+            None => {}
+        }
+        if self.chunked {
+            head.push_str("Transfer-Encoding: chunked\r\n");
+        }
+        head.push_str("\r\n");
+
+        let mut bytes = head.into_bytes();
+        if self.chunked {
+            if !body.is_empty() {
+                bytes.extend_from_slice(format!("{:x}\r\n", body.len()).as_bytes());
+                bytes.extend_from_slice(body);
+                bytes.extend_from_slice(b"\r\n");
+            }
+            bytes.extend_from_slice(b"0\r\n\r\n");
+        } else {
+            bytes.extend_from_slice(body);
+        }
+        bytes
+    }
+
+    /// Sends `request` with this framing and reads the response. Only `http://` URLs
+    /// are supported.
+    pub async fn execute(&self, request: &reqwest::Request) -> Result<reqwest::Response> {
+        let url = request.url();
+        if url.scheme() != "http" {
+            bail!("--raw-framing only supports http:// URLs, not {}", url);
+        }
+        let host = url.host_str().ok_or_else(|| anyhow!("{} has no host", url))?;
+        let port = url.port_or_known_default().unwrap_or(80);
+        let exchange = async {
+            let connect = TcpStream::connect((host, port));
+            let mut stream = match self.connect_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, connect)
+                    .await
+                    .with_context(|| format!("Raw connection to {} timed out", url))??,
+                None => connect.await?,
+            };
+            stream.write_all(&self.encode(request)).await?;
+            read_response(&mut stream, self.max_response_size).await
+        };
+        let (raw, truncated) = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, exchange)
+                .await
+                .with_context(|| format!("Raw request to {} timed out", url))??,
+            None => exchange.await?,
+        };
+        parse_response(&raw, truncated)
+    }
+}
+
+/// Reads a response until the server closes the connection, or until its body has
+/// `max_body` bytes. Returns the bytes read and whether the body was cut off.
+async fn read_response(stream: &mut TcpStream, max_body: Option<usize>) -> Result<(Vec<u8>, bool)> {
+    let mut raw = Vec::new();
+    let mut buf = [0; 8192];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok((raw, false));
+        }
+        raw.extend_from_slice(&buf[..n]);
+        let Some(head_end) = find_head_end(&raw) else {
+            if raw.len() > MAX_HEAD_SIZE {
+                bail!("Response headers larger than {} bytes", MAX_HEAD_SIZE);
+            }
+            continue;
+        };
+        if let Some(max) = max_body
+            && raw.len() >= head_end + 4 + max
+        {
+            raw.truncate(head_end + 4 + max);
+            return Ok((raw, true));
+        }
+    }
+}
+
+/// The position of the blank line that ends the response headers.
+fn find_head_end(raw: &[u8]) -> Option<usize> {
+    raw.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+/// Parses an HTTP/1.1 response read from a closed connection, or one whose body was
+/// cut off after `max_response_size` bytes (`truncated`).
+fn parse_response(raw: &[u8], truncated: bool) -> Result<reqwest::Response> {
+    let head_end =
+        find_head_end(raw).ok_or_else(|| anyhow!("Incomplete response: no end of headers"))?;
+    let head = String::from_utf8_lossy(&raw[..head_end]);
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|status_line| status_line.split(' ').nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| anyhow!("Invalid status line"))?;
+
+    let mut response = http::Response::builder().status(status);
+    let mut chunked = false;
+    let mut content_length = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+            continue;
+        }
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse::<usize>().ok();
+        }
+        response = response.header(name, value);
+    }
+
+    let rest = &raw[head_end + 4..];
+    let body = if chunked {
+        decode_chunked(rest, truncated)?
+    } else {
+        rest[..content_length.unwrap_or(rest.len()).min(rest.len())].to_vec()
+    };
+    Ok(reqwest::Response::from(response.body(body)?))
+}
+
+/// Decodes a chunked body, ignoring chunk extensions and trailers. The body of a
+/// `truncated` response is decoded as far as it goes.
+fn decode_chunked(mut rest: &[u8], truncated: bool) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let Some(line_end) = rest.windows(2).position(|window| window == b"\r\n") else {
+            if truncated {
+                return Ok(body);
+            }
+            bail!("Incomplete chunked body");
+        };
+        let size_line = String::from_utf8_lossy(&rest[..line_end]);
+        let size_hex = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| anyhow!("Invalid chunk size '{}'", size_hex))?;
+        rest = &rest[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        let Some(chunk) = rest.get(..size) else {
+            if truncated {
+                body.extend_from_slice(rest);
+                return Ok(body);
+            }
+            bail!("Incomplete chunked body");
+        };
+        body.extend_from_slice(chunk);
+        rest = rest.get(size + 2..).unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RawFraming, decode_chunked};
+
+    #[test]
+    fn test_encode_overrides_framing() {
+        let request = reqwest::Client::new()
+            .post("http://example.com:8080/login?next=/")
+            .header("X-Test", "1")
+            .body("user=admin")
+            .build()
+            .unwrap();
+        let framing = RawFraming {
+            content_length: Some(4),
+            chunked: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            String::from_utf8(framing.encode(&request)).unwrap(),
+            "POST /login?next=/ HTTP/1.1\r\n\
+             Host: example.com:8080\r\n\
+             x-test: 1\r\n\
+             Connection: close\r\n\
+             Content-Length: 4\r\n\
+             Transfer-Encoding: chunked\r\n\
+             \r\n\
+             a\r\nuser=admin\r\n0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_decode_chunked() {
+        assert_eq!(
+            decode_chunked(b"3\r\nabc\r\n2;ext=1\r\nde\r\n0\r\n\r\n", false).unwrap(),
+            b"abcde"
+        );
+        assert!(decode_chunked(b"3\r\nabc\r\n5\r\nde", false).is_err());
+        assert_eq!(decode_chunked(b"3\r\nabc\r\n5\r\nde", true).unwrap(), b"abcde");
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use tokio::sync::Semaphore;
+
+/// At most this many hosts are kept in the [`DnsCache`].
+const DNS_CACHE_MAX_HOSTS: usize = 4096;
+
+/// At most this many lookups of the [`DnsCache`] run at the same time.
+const DNS_MAX_CONCURRENT_LOOKUPS: usize = 16;
+
+/// The DNS resolver of the client: host names pinned to an address with `--resolve`,
+/// and for other names a lookup, cached with `--dns-cache-ttl`.
+///
+/// Only the address is pinned: the URL still decides the port, the `Host` header and
+/// the TLS server name (SNI). Every connection goes through the resolver, so requests
+/// to a pinned name, including redirects followed to it, use the pinned address.
+#[derive(Default)]
+pub struct DnsResolver {
+    /// Lowercase host names, or `*.domain` for every subdomain of `domain`, in the
+    /// order given. The first match wins.
+    pins: Vec<(String, IpAddr)>,
+    cache: Option<Arc<DnsCache>>,
+}
+
+impl DnsResolver {
+    /// Caches the lookups of names that are not pinned for `ttl`.
+    pub fn with_cache(mut self, ttl: Duration) -> Self {
+        self.cache = Some(Arc::new(DnsCache::new(ttl)));
+        self
+    }
+
+    /// Adds a pin given as `HOST:ADDRESS`, e.g. `app.example.com:10.0.0.5`,
+    /// `*.example.com:10.0.0.5` or `app.example.com:[::1]`.
+    pub fn pin(&mut self, spec: &str) -> Result<(), String> {
+        let invalid = |reason: &str| format!("Invalid --resolve '{}': {}", spec, reason);
+        let (host, address) = spec
+            .split_once(':')
+            .ok_or_else(|| invalid("expected HOST:ADDRESS, e.g. app.example.com:10.0.0.5"))?;
+        let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+        let name = host.strip_prefix("*.").unwrap_or(&host);
+        if name.is_empty() || name.contains('*') {
+            return Err(invalid("expected a host name or *.domain"));
+        }
+        let address = address.trim();
+        let address = address
+            .strip_prefix('[')
+            .and_then(|address| address.strip_suffix(']'))
+            .unwrap_or(address)
+            .parse::<IpAddr>()
+            .map_err(|_| invalid("expected an IPv4 or IPv6 address"))?;
+        self.pins.push((host, address));
+        Ok(())
+    }
+
+    /// Returns the address pinned for `host`, if any.
+    pub fn lookup(&self, host: &str) -> Option<IpAddr> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.pins.iter().find_map(|(pattern, address)| {
+            let matches = match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|subdomain| subdomain.ends_with('.')),
+                None => host == *pattern,
+            };
+            matches.then_some(*address)
+        })
+    }
+}
+
+impl Resolve for DnsResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let pinned = self.lookup(&host);
+        let cache = self.cache.clone();
+        Box::pin(async move {
+            // The connector replaces port 0 with the port of the URL.
+            let addrs: Addrs = match (pinned, cache) {
+                (Some(address), _) => Box::new(std::iter::once(SocketAddr::new(address, 0))),
+                (None, Some(cache)) => {
+                    let lookup = || async {
+                        Ok(tokio::net::lookup_host((host.as_str(), 0)).await?.collect())
+                    };
+                    Box::new(cache.lookup(&host, lookup).await?.into_iter())
+                }
+                (None, None) => Box::new(tokio::net::lookup_host((host, 0)).await?),
+            };
+            Ok(addrs)
+        })
+    }
+}
+
+/// The addresses of a host and when they expire.
+struct CachedAnswer {
+    addrs: Vec<SocketAddr>,
+    expires: Instant,
+}
+
+/// DNS answers shared by all requests, so that new connections to the same host do
+/// not repeat the lookup within the TTL. Lookups of the same host wait for the one in
+/// progress, and at most [`DNS_MAX_CONCURRENT_LOOKUPS`] different hosts are looked up
+/// at the same time, e.g. when fuzzing subdomains.
+pub struct DnsCache {
+    ttl: Duration,
+    hosts: std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<Option<CachedAnswer>>>>>,
+    lookups: Semaphore,
+}
+
+impl DnsCache {
+    pub fn new(ttl: Duration) -> Self {
+        DnsCache {
+            ttl,
+            hosts: Default::default(),
+            lookups: Semaphore::new(DNS_MAX_CONCURRENT_LOOKUPS),
+        }
+    }
+
+    /// Returns the cached addresses of `host`, or looks them up with `resolve` and
+    /// caches them. Failed lookups are not cached.
+    pub async fn lookup<F, Fut>(&self, host: &str, resolve: F) -> io::Result<Vec<SocketAddr>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = io::Result<Vec<SocketAddr>>>,
+    {
+        let slot = {
+            let mut hosts = self.hosts.lock().expect("DNS cache lock poisoned");
+            if hosts.len() >= DNS_CACHE_MAX_HOSTS && !hosts.contains_key(host) {
+                // Make room: drop the expired answers, or all of them when every
+                // answer is still fresh. Hosts being looked up are kept.
+                let now = Instant::now();
+                hosts.retain(|_, slot| {
+                    slot.try_lock()
+                        .map_or(true, |answer| answer.as_ref().is_some_and(|a| a.expires > now))
+                });
+                if hosts.len() >= DNS_CACHE_MAX_HOSTS {
+                    hosts.retain(|_, slot| slot.try_lock().is_err());
+                }
+            }
+            hosts.entry(host.to_string()).or_default().clone()
+        };
+        let mut answer = slot.lock().await;
+        if let Some(cached) = answer.as_ref()
+            && cached.expires > Instant::now()
+        {
+            return Ok(cached.addrs.clone());
+        }
+        let _permit = self.lookups.acquire().await.expect("DNS lookup semaphore closed");
+        let addrs = resolve().await?;
+        *answer = Some(CachedAnswer {
+            addrs: addrs.clone(),
+            expires: Instant::now() + self.ttl,
+        });
+        Ok(addrs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::{DnsCache, DnsResolver};
+
+    #[test]
+    fn test_pinned_resolver_matches_hosts_and_subdomains() {
+        let mut resolver = DnsResolver::default();
+        resolver.pin("App.Example.com:10.0.0.1").unwrap();
+        resolver.pin("*.example.com:10.0.0.2").unwrap();
+        resolver.pin("v6.test:[::1]").unwrap();
+
+        assert_eq!(resolver.lookup("app.example.com"), Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(resolver.lookup("api.example.com."), Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(resolver.lookup("a.b.example.com"), Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(resolver.lookup("example.com"), None);
+        assert_eq!(resolver.lookup("badexample.com"), None);
+        assert_eq!(resolver.lookup("v6.test"), Some("::1".parse().unwrap()));
+
+        assert!(resolver.pin("example.com").is_err());
+        assert!(resolver.pin("example.com:not-an-ip").is_err());
+        assert!(resolver.pin("*:10.0.0.1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dns_cache_resolves_a_host_once_within_the_ttl() {
+        let lookups = AtomicUsize::new(0);
+        let resolve = || async {
+            lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(vec!["10.0.0.1:0".parse().unwrap()])
+        };
+
+        let cache = DnsCache::new(Duration::from_secs(60));
+        for _ in 0..3 {
+            let addrs = cache.lookup("app.example.com", resolve).await.unwrap();
+            assert_eq!(addrs, ["10.0.0.1:0".parse().unwrap()]);
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+        cache.lookup("api.example.com", resolve).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        let expired = DnsCache::new(Duration::ZERO);
+        expired.lookup("app.example.com", resolve).await.unwrap();
+        expired.lookup("app.example.com", resolve).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 4);
+    }
+}
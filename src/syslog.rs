@@ -0,0 +1,165 @@
+#[cfg(unix)]
+use std::path::Path;
+
+use clap::ValueEnum;
+#[cfg(unix)]
+use dirnutek::ScanEvent;
+#[cfg(unix)]
+use tokio::net::UnixDatagram;
+#[cfg(unix)]
+use tokio::sync::mpsc;
+
+/// Sockets of the local syslog daemon on Linux and macOS.
+#[cfg(unix)]
+const SYSLOG_SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+
+/// Syslog facility used for the messages sent by `--syslog`.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum Facility {
+    User,
+    Daemon,
+    Auth,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl Facility {
+    #[cfg(unix)]
+    fn code(self) -> u8 {
+        match self {
+            Facility::User => 1,
+            Facility::Daemon => 3,
+            Facility::Auth => 4,
+            Facility::Local0 => 16,
+            Facility::Local1 => 17,
+            Facility::Local2 => 18,
+            Facility::Local3 => 19,
+            Facility::Local4 => 20,
+            Facility::Local5 => 21,
+            Facility::Local6 => 22,
+            Facility::Local7 => 23,
+        }
+    }
+}
+
+/// Sends messages to the local syslog daemon in the RFC 3164 format.
+#[cfg(unix)]
+pub struct SyslogSink {
+    socket: UnixDatagram,
+    facility: Facility,
+    tag: String,
+}
+
+#[cfg(unix)]
+impl SyslogSink {
+    /// Connects to the first syslog socket that accepts a connection.
+    pub fn connect(facility: Facility, tag: &str) -> std::io::Result<Self> {
+        let mut last_error = None;
+        for path in SYSLOG_SOCKETS {
+            match Self::connect_to(Path::new(path), facility, tag) {
+                Ok(sink) => return Ok(sink),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.expect("SYSLOG_SOCKETS is not empty"))
+    }
+
+    pub fn connect_to(path: &Path, facility: Facility, tag: &str) -> std::io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self {
+            socket,
+            facility,
+            tag: tag.to_string(),
+        })
+    }
+
+    /// Logs a message with the `info` severity.
+    pub async fn info(&self, message: &str) -> std::io::Result<()> {
+        const SEVERITY_INFO: u8 = 6;
+        let line = format!(
+            "<{}>{}[{}]: {}",
+            self.facility.code() * 8 + SEVERITY_INFO,
+            self.tag,
+            std::process::id(),
+            message
+        );
+        self.socket.send(line.as_bytes()).await.map(|_| ())
+    }
+
+    /// Logs every found URL and forwards all events to the returned receiver, so the
+    /// console or TUI consumer works unchanged.
+    pub fn tee(self, mut rx: mpsc::Receiver<ScanEvent>) -> mpsc::Receiver<ScanEvent> {
+        let (tx, tee_rx) = mpsc::channel(100);
+        tokio::spawn(async move {
+            let mut failed = false;
+            while let Some(event) = rx.recv().await {
+                if let ScanEvent::FoundUrl(finding) = &event
+                    && let Err(e) = self.info(&finding.to_string()).await
+                    && !failed
+                {
+                    eprintln!("Warning: Could not write to syslog: {}", e);
+                    failed = true;
+                }
+                if tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+        tee_rx
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{Facility, SyslogSink};
+    use dirnutek::{Finding, ScanEvent};
+    use std::time::Duration;
+    use tokio::net::UnixDatagram;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_syslog_tee_logs_found_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.sock");
+        let daemon = UnixDatagram::bind(&path).unwrap();
+
+        let sink = SyslogSink::connect_to(&path, Facility::Local0, "dirnutek").unwrap();
+        let (tx, rx) = mpsc::channel(10);
+        let mut rx = sink.tee(rx);
+        let completed = ScanEvent::RequestCompleted {
+            elapsed: Duration::from_millis(12),
+        };
+        tx.send(completed.clone()).await.unwrap();
+        let finding = Finding {
+            url: "http://example.com/admin".to_string(),
+            status: 200,
+            words: Some(0),
+            chars: Some(0),
+            lines: Some(0),
+            ..Default::default()
+        };
+        tx.send(ScanEvent::FoundUrl(finding)).await.unwrap();
+        drop(tx);
+
+        assert_eq!(rx.recv().await, Some(completed));
+        assert!(matches!(rx.recv().await, Some(ScanEvent::FoundUrl(_))));
+        assert_eq!(rx.recv().await, None);
+
+        let mut buf = [0u8; 256];
+        let n = daemon.recv(&mut buf).await.unwrap();
+        let message = String::from_utf8_lossy(&buf[..n]);
+        assert!(message.starts_with("<134>dirnutek["), "{}", message);
+        assert!(
+            message.ends_with("]: [200 OK] http://example.com/admin [0W, 0C, 0L]"),
+            "{}",
+            message
+        );
+    }
+}
@@ -33,6 +33,15 @@ pub struct App {
     pub start_time: Instant,
     pub scan_finished: bool,
     pub scan_stopped: bool, // New field for user-initiated stop
+    pub status_message: Option<String>,
+    /// When the current pause between bursts ends, if `--burst` is pausing.
+    pub burst_resumes_at: Option<Instant>,
+    /// The next recursion depth and when it starts, if `--level-delay` is pausing.
+    pub level_resumes_at: Option<(usize, Instant)>,
+    /// The sum of the response times of the completed requests.
+    pub total_latency: Duration,
+    /// The longest response time of a completed request.
+    pub max_latency: Duration,
 }
 
 impl Default for App {
@@ -47,11 +56,23 @@ impl Default for App {
             start_time: Instant::now(),
             scan_finished: false,
             scan_stopped: false,
+            status_message: None,
+            burst_resumes_at: None,
+            level_resumes_at: None,
+            total_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
         }
     }
 }
 
 impl App {
+    /// The average response time of the completed requests.
+    pub fn average_latency(&self) -> Duration {
+        self.total_latency
+            .checked_div(self.requests_completed as u32)
+            .unwrap_or_default()
+    }
+
     /// Adds a found URL to the list and updates the selection to it.
     pub fn add_found_url(&mut self, url: String) {
         self.found_urls.push(url);
@@ -59,6 +80,30 @@ impl App {
         self.list_state.select(Some(new_index));
     }
 
+    /// Returns the entry highlighted in the list. The list is rendered newest first,
+    /// so the selection counts from the end of `found_urls`.
+    pub fn selected_entry(&self) -> Option<&str> {
+        let selected = self.list_state.selected()?;
+        let index = self.found_urls.len().checked_sub(selected + 1)?;
+        self.found_urls.get(index).map(String::as_str)
+    }
+
+    /// Copies the URL of the selected entry to the clipboard and reports the outcome
+    /// in the status line.
+    fn copy_selected_url(&mut self, clipboard: &mut ClipboardHandle) {
+        let url = self
+            .selected_entry()
+            .and_then(|entry| dirnutek::extract_urls(entry).next())
+            .map(str::to_string);
+        self.status_message = Some(match url {
+            Some(url) => match clipboard.copy(&url) {
+                Ok(()) => format!("Copied {}", url),
+                Err(e) => format!("Clipboard unavailable: {}", e),
+            },
+            None => "No URL selected.".to_string(),
+        });
+    }
+
     /// Calculates requests per second.
     pub fn rps(&self) -> f64 {
         let elapsed = self.start_time.elapsed().as_secs_f64();
@@ -79,6 +124,32 @@ impl App {
     }
 }
 
+/// Keeps the system clipboard open for the whole session: on X11 the copied text is
+/// only served while the clipboard handle is alive.
+#[derive(Default)]
+struct ClipboardHandle {
+    #[cfg(feature = "clipboard")]
+    inner: Option<arboard::Clipboard>,
+}
+
+impl ClipboardHandle {
+    #[cfg(feature = "clipboard")]
+    fn copy(&mut self, text: &str) -> Result<(), String> {
+        let clipboard = match &mut self.inner {
+            Some(clipboard) => clipboard,
+            None => self
+                .inner
+                .insert(arboard::Clipboard::new().map_err(|e| e.to_string())?),
+        };
+        clipboard.set_text(text).map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    fn copy(&mut self, _text: &str) -> Result<(), String> {
+        Err("built without the `clipboard` feature".to_string())
+    }
+}
+
 pub fn init() -> io::Result<Tui> {
     stdout().execute(EnterAlternateScreen)?;
     enable_raw_mode()?;
@@ -93,12 +164,14 @@ pub fn restore() -> io::Result<()> {
     Ok(())
 }
 
+#[allow(clippy::collapsible_if)]
 pub async fn run_tui(
     terminal: &mut Tui,
     mut rx_events: mpsc::Receiver<ScanEvent>,
     tx_control: broadcast::Sender<ControlEvent>,
 ) -> io::Result<()> {
     let mut app = App::default();
+    let mut clipboard = ClipboardHandle::default();
     let tick_rate = Duration::from_millis(250);
     let mut should_exit = false; // Local flag to control loop exit
 
@@ -111,10 +184,12 @@ pub async fn run_tui(
             // 1. Scan events
             Some(event) = rx_events.recv() => {
                 match event {
-                    ScanEvent::FoundUrl(url) => app.add_found_url(url),
-                    ScanEvent::RequestCompleted => {
+                    ScanEvent::FoundUrl(finding) => app.add_found_url(format!("{:#}", finding)),
+                    ScanEvent::RequestCompleted { elapsed } => {
                         app.requests_completed += 1;
                         app.current_word_index += 1;
+                        app.total_latency += elapsed;
+                        app.max_latency = app.max_latency.max(elapsed);
                     }
                     ScanEvent::ErrorOccurred(msg) => {
                         app.errors_occurred += 1;
@@ -130,6 +205,29 @@ pub async fn run_tui(
                         app.scan_finished = false;
                         app.scan_stopped = false;
                     },
+                    ScanEvent::ReplayFinished { replayed, recovered } => {
+                        app.add_found_url(format!(
+                            "Warning: Replayed {} failed requests, {} recovered.",
+                            replayed, recovered
+                        ));
+                    },
+                    ScanEvent::BurstPaused { pause } => {
+                        app.burst_resumes_at = Some(Instant::now() + pause);
+                    },
+                    ScanEvent::BurstResumed => app.burst_resumes_at = None,
+                    ScanEvent::LevelPaused { depth, pause } => {
+                        app.level_resumes_at = Some((depth, Instant::now() + pause));
+                    },
+                    ScanEvent::RecursionPreview { directories, requests } => {
+                        app.add_found_url(format!(
+                            "Recursion preview: {} directories, about {} requests:",
+                            directories.len(),
+                            requests
+                        ));
+                        for directory in directories {
+                            app.add_found_url(format!("Would recurse into {}", directory));
+                        }
+                    },
                     ScanEvent::ScanFinished => app.scan_finished = true,
                     ScanEvent::ScanStopped => app.scan_stopped = true,
                 }
@@ -162,6 +260,7 @@ pub async fn run_tui(
                                 KeyCode::PageDown => app.scroll_page_down(),
                                 KeyCode::Home => app.scroll_to_top(),
                                 KeyCode::End => app.scroll_to_bottom(),
+                                KeyCode::Char('c') => app.copy_selected_url(&mut clipboard),
                                 _ => {}
                             }
                         }
@@ -207,7 +306,7 @@ fn ui(frame: &mut Frame, app: &mut App) {
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(inner_stats_area);
 
-    let stats_table_rows = vec![
+    let mut stats_table_rows = vec![
         Row::new(vec![
             Cell::from("Progress:").bold(),
             Cell::from(format!(
@@ -240,8 +339,41 @@ fn ui(frame: &mut Frame, app: &mut App) {
             Cell::from("Errors:").bold(),
             Cell::from(format!("{}", app.errors_occurred)).fg(Color::Red),
         ]),
+        Row::new(vec![
+            Cell::from("Latency:").bold(),
+            Cell::from(format!(
+                "avg {} ms, max {} ms",
+                app.average_latency().as_millis(),
+                app.max_latency.as_millis()
+            )),
+        ]),
     ];
 
+    if let Some(resumes_at) = app.burst_resumes_at {
+        stats_table_rows.push(Row::new(vec![
+            Cell::from("Burst:").bold(),
+            Cell::from(format!(
+                "paused, resuming in {}s",
+                resumes_at.saturating_duration_since(Instant::now()).as_secs()
+            ))
+            .fg(Color::Yellow),
+        ]));
+    }
+
+    if let Some((depth, resumes_at)) = app.level_resumes_at
+        && resumes_at > Instant::now()
+    {
+        stats_table_rows.push(Row::new(vec![
+            Cell::from("Level pause:").bold(),
+            Cell::from(format!(
+                "depth {} starts in {}s",
+                depth,
+                resumes_at.saturating_duration_since(Instant::now()).as_secs()
+            ))
+            .fg(Color::Yellow),
+        ]));
+    }
+
     let stats_table =
         Table::new(stats_table_rows, [Constraint::Length(18), Constraint::Min(10)]).column_spacing(1);
 
@@ -254,7 +386,11 @@ fn ui(frame: &mut Frame, app: &mut App) {
     } else {
         Line::from("Scanning...".yellow().bold())
     };
-    let status_widget = Paragraph::new(status_text);
+    let mut status_lines = vec![status_text];
+    if let Some(message) = &app.status_message {
+        status_lines.push(Line::from(message.clone().italic()));
+    }
+    let status_widget = Paragraph::new(status_lines);
     frame.render_widget(status_widget, stats_layout[1]);
 
 
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use crate::is_malformed_word;
+
+/// What [`clean`] changed in a wordlist.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CleanStats {
+    pub kept: usize,
+    pub comments: usize,
+    pub duplicates: usize,
+    /// Entries that look malformed; removed only with `drop_invalid`.
+    pub invalid: usize,
+    pub invalid_dropped: bool,
+}
+
+impl fmt::Display for CleanStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Kept {} words; removed {} comments and {} duplicates; {} invalid entries {}.",
+            self.kept,
+            self.comments,
+            self.duplicates,
+            self.invalid,
+            if self.invalid_dropped { "removed" } else { "kept" }
+        )
+    }
+}
+
+/// Cleans trimmed, non-empty wordlist lines as read by `read_wordlist`: drops `#`
+/// comments and duplicates (keeping the first occurrence), optionally lowercases the
+/// words and drops malformed entries.
+pub fn clean(lines: Vec<String>, lowercase: bool, drop_invalid: bool) -> (Vec<String>, CleanStats) {
+    let mut stats = CleanStats {
+        invalid_dropped: drop_invalid,
+        ..Default::default()
+    };
+    let mut seen = std::collections::HashSet::new();
+    let mut words = Vec::new();
+    for line in lines {
+        if line.starts_with('#') {
+            stats.comments += 1;
+            continue;
+        }
+        let word = if lowercase { line.to_lowercase() } else { line };
+        if is_malformed_word(&word) {
+            stats.invalid += 1;
+            if drop_invalid {
+                continue;
+            }
+        }
+        if !seen.insert(word.clone()) {
+            stats.duplicates += 1;
+            continue;
+        }
+        words.push(word);
+    }
+    stats.kept = words.len();
+    (words, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clean;
+
+    #[test]
+    fn test_clean_wordlist() {
+        let lines = ["# common paths", "Admin", "admin", "login", "Admin", "<html>", "login"]
+            .map(String::from)
+            .to_vec();
+
+        let (words, stats) = clean(lines.clone(), false, false);
+        assert_eq!(words, ["Admin", "admin", "login", "<html>"]);
+        assert_eq!(
+            stats.to_string(),
+            "Kept 4 words; removed 1 comments and 2 duplicates; 1 invalid entries kept."
+        );
+
+        let (words, stats) = clean(lines, true, true);
+        assert_eq!(words, ["admin", "login"]);
+        assert_eq!((stats.duplicates, stats.invalid), (3, 1));
+    }
+}
@@ -1,7 +1,17 @@
 #![allow(unused_imports)]
+// The older tests use `.args(&[..])` and `format!` forms that these lints flag.
+#![allow(
+    clippy::needless_borrow,
+    clippy::needless_borrows_for_generic_args,
+    clippy::single_char_add_str,
+    clippy::to_string_in_format_args,
+    clippy::useless_format
+)]
 
 use assert_cmd::Command;
-use httptest::matchers::{all_of, contains, eq, request};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use httptest::matchers::{all_of, contains, eq, matches, request};
 use httptest::{Expectation, Server, responders};
 use std::io::Write;
 use std::sync::{
@@ -102,6 +112,41 @@ fn test_read_empty_wordlist() {
         .stdout(predicates::str::contains("Read 0 words from wordlist."));
 }
 
+#[test]
+fn test_fail_on_zero_words() {
+    let wordlist_file = create_temp_wordlist("\n  \n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", "http://example.com", "-w", wordlist_path, "--fail-on-zero-words"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No words to scan: the wordlist"))
+        .stdout(predicates::str::contains("Starting scan").count(0));
+}
+
+#[test]
+fn test_cli_concurrency_auto_prints_detected_value() {
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", "http://127.0.0.1:9/", "-w", wordlist_path, "-c", "auto", "--preview"])
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"# Auto-detected concurrency: \d+ \(\d+ CPUs").unwrap())
+        .stdout(predicates::str::contains("#   Concurrency: auto").count(0));
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", "http://127.0.0.1:9/", "-w", wordlist_path, "-c", "fast"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid concurrency value"));
+}
+
 #[test]
 fn test_read_wordlist_with_empty_lines() {
     let wordlist_file = create_temp_wordlist("word1\n\nword2\n  \nword3");
@@ -122,6 +167,36 @@ fn test_read_wordlist_with_empty_lines() {
         .stdout(predicates::str::contains("Read 3 words from wordlist."));
 }
 
+#[test]
+fn test_read_gzipped_wordlist() {
+    // Two members, as `cat a.gz b.gz` gives.
+    let mut compressed = Vec::new();
+    for part in ["word1\n\nword2\n", "  \nword3\n"] {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(part.as_bytes()).unwrap();
+        compressed.extend(encoder.finish().unwrap());
+    }
+    let mut gz_file = tempfile::Builder::new().suffix(".gz").tempfile().unwrap();
+    gz_file.write_all(&compressed).unwrap();
+    // Detected by the magic bytes without the extension, too.
+    let mut bare_file = tempfile::NamedTempFile::new().unwrap();
+    bare_file.write_all(&compressed).unwrap();
+
+    for (path, extra_args) in [
+        (gz_file.path(), vec![]),
+        (bare_file.path(), vec![]),
+        (gz_file.path(), vec!["--low-memory"]),
+    ] {
+        Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(["-u", "http://example.com", "-w", path.to_str().unwrap(), "--preview"])
+            .args(extra_args)
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("Read 3 words from wordlist."));
+    }
+}
+
 #[test]
 fn test_cli_output_formatting() {
     let server = Server::run();
@@ -172,7 +247,9 @@ fn test_cli_output_formatting() {
     assert!(stdout_str.contains(
         &("[301 Moved Permanently] ".to_owned()
             + &server_url
-            + "moved -> /new_location [0W, 0C, 0L]")
+            + "moved -> "
+            + &server_url
+            + "new_location [0W, 0C, 0L]")
     ));
     assert!(
         stdout_str
@@ -183,6 +260,346 @@ fn test_cli_output_formatting() {
     assert!(!stdout_str.contains("[404]"));
 }
 
+#[test]
+fn test_cli_output_format_json() {
+    let server = Server::run();
+
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/found"))
+            .respond_with(responders::status_code(200).body("hello world")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/moved"))
+            .respond_with(responders::status_code(301).insert_header("Location", "/new_location")),
+    );
+
+    let wordlist_file = create_temp_wordlist("found\nmoved\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &server_url,
+            "-w",
+            wordlist_path,
+            "--output-format",
+            "json",
+            "--sort-output",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    let mut lines: Vec<&str> = stdout_str.lines().collect();
+    let completed: serde_json::Value = serde_json::from_str(lines.pop().unwrap()).unwrap();
+    assert_eq!(completed, serde_json::json!({ "event": "completed", "hits": 2 }));
+    let findings: Vec<serde_json::Value> = lines
+        .into_iter()
+        .map(|line| {
+            let mut finding: serde_json::Value =
+                serde_json::from_str(line).expect("Invalid JSON line");
+            let fields = finding.as_object_mut().unwrap();
+            for varying in ["timestamp", "ttfb_ms", "total_ms"] {
+                assert!(fields.remove(varying).is_some(), "{}", varying);
+            }
+            finding
+        })
+        .collect();
+
+    assert_eq!(
+        findings,
+        vec![
+            serde_json::json!({
+                "url": server_url.clone() + "found",
+                "status": 200,
+                "redirect_target": null,
+                "words": 2,
+                "chars": 11,
+                "lines": 1,
+                "method": "GET",
+                "classification": "found",
+            }),
+            serde_json::json!({
+                "url": server_url.clone() + "moved",
+                "status": 301,
+                "redirect_target": server_url.clone() + "new_location",
+                "words": 0,
+                "chars": 0,
+                "lines": 0,
+                "method": "GET",
+                "classification": "redirect",
+            }),
+        ]
+    );
+    assert!(!stdout_str.contains("[200 OK]"));
+}
+
+#[test]
+fn test_cli_output_file_truncates_or_appends() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .times(3)
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/missing"))
+            .times(3)
+            .respond_with(responders::status_code(404)),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\nmissing\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+    let output_file = tempfile::NamedTempFile::new().unwrap();
+    let output_path = output_file.path().to_str().unwrap();
+    let hit = format!("[200 OK] {}admin [0W, 0C, 0L]\n", server_url);
+
+    for (extra_args, expected) in [
+        (vec![], hit.clone()),
+        (vec![], hit.clone()),
+        (vec!["--append"], hit.repeat(2)),
+    ] {
+        Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(["-u", &server_url, "-w", wordlist_path, "-o", output_path])
+            .args(extra_args)
+            .assert()
+            .success()
+            .stdout(predicates::str::contains(hit.as_str()))
+            .stdout(predicates::str::contains("# Completed with 1 hits."));
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), expected);
+    }
+}
+
+/// Starts a scan that finds `/admin` at once and then waits two seconds for `/slow`,
+/// and returns the --output file content while the scan is still running.
+fn output_during_scan(extra_args: &[&str]) -> String {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/slow")).respond_with(
+            responders::delay_and_then(Duration::from_secs(2), responders::status_code(404)),
+        ),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\nslow\n");
+    let output_file = tempfile::NamedTempFile::new().unwrap();
+    let output_path = output_file.path().to_str().unwrap();
+    let mut scan = std::process::Command::new(assert_cmd::cargo::cargo_bin("dirnutek"))
+        .args(["-u", &server.url("/").to_string(), "-w"])
+        .arg(wordlist_file.path())
+        .args(["-c", "1", "-o", output_path])
+        .args(extra_args)
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(1000));
+    let during = std::fs::read_to_string(output_path).unwrap();
+    assert!(scan.wait().unwrap().success());
+    assert!(std::fs::read_to_string(output_path).unwrap().contains("admin"));
+    during
+}
+
+#[test]
+fn test_cli_output_is_flushed_per_result_or_per_interval() {
+    assert!(output_during_scan(&[]).contains("admin"));
+    assert_eq!(output_during_scan(&["--flush-interval", "60"]), "");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_output_streams_live_to_a_fifo() {
+    use std::io::BufRead;
+
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/slow")).respond_with(
+            responders::delay_and_then(Duration::from_secs(2), responders::status_code(404)),
+        ),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\nslow\n");
+    let dir = tempfile::tempdir().unwrap();
+    let fifo_path = dir.path().join("hits");
+    let c_path = std::ffi::CString::new(fifo_path.to_str().unwrap()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0, "mkfifo failed");
+
+    // --flush-interval is ignored for a FIFO.
+    let mut scan = std::process::Command::new(assert_cmd::cargo::cargo_bin("dirnutek"))
+        .args(["-u", &server.url("/").to_string(), "-w"])
+        .arg(wordlist_file.path())
+        .args(["-c", "1", "--flush-interval", "60", "-o"])
+        .arg(&fifo_path)
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    // The scan waits for the reader instead of failing or blocking forever.
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(scan.try_wait().unwrap().is_none());
+
+    let mut reader = std::io::BufReader::new(std::fs::File::open(&fifo_path).unwrap());
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(line.contains("admin"), "{}", line);
+    assert!(scan.try_wait().unwrap().is_none(), "the hit arrived only at the end of the scan");
+
+    line.clear();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "", "the FIFO is closed when the scan ends");
+    assert!(scan.wait().unwrap().success());
+}
+
+#[test]
+fn test_cli_show_protected_marks_denied_paths() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(403)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/login"))
+            .respond_with(responders::status_code(200)),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\nlogin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", &server_url, "-w", wordlist_path, "--show-protected"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!(
+            "[403 Forbidden] {}admin [0W, 0C, 0L] [protected]\n",
+            server_url
+        )))
+        .stdout(predicates::str::contains(format!(
+            "[200 OK] {}login [0W, 0C, 0L]\n",
+            server_url
+        )));
+}
+
+#[test]
+fn test_cli_output_format_csv_prints_header_once() {
+    let server1 = Server::run();
+    server1.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(200).body("a, b")),
+    );
+    let server2 = Server::run();
+    server2.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(301).insert_header("Location", "/admin,old")),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let url1 = server1.url("/").to_string();
+    let url2 = server2.url("/").to_string();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &url1,
+            "-u",
+            &url2,
+            "-w",
+            wordlist_path,
+            "--output-format",
+            "csv",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    let mut rows: Vec<&str> = stdout_str.lines().filter(|line| !line.starts_with('#')).collect();
+    assert_eq!(rows.remove(0), "url,status,redirect,words,chars,lines");
+    rows.sort();
+    let mut expected = vec![
+        format!("{}admin,200,,2,4,1", url1),
+        format!("{}admin,301,\"{}admin,old\",0,0,0", url2, url2),
+    ];
+    expected.sort();
+    assert_eq!(rows, expected);
+}
+
+#[test]
+fn test_cli_ndjson_lines_parse_one_by_one() {
+    let server = Server::run();
+    for path in ["/admin", "/login"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200)),
+        );
+    }
+
+    let wordlist_file = create_temp_wordlist("admin\nlogin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", &server_url, "-w", wordlist_path, "--output-format", "ndjson"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    let mut urls = Vec::new();
+    for line in stdout_str.lines() {
+        let finding: serde_json::Value = serde_json::from_str(line).expect("Invalid JSON line");
+        if finding["event"] == "completed" {
+            continue;
+        }
+        assert_eq!(finding["method"], "GET");
+        let timestamp = finding["timestamp"].as_str().unwrap();
+        assert!(timestamp.ends_with('Z') && timestamp.contains('T'), "{}", timestamp);
+        urls.push(finding["url"].as_str().unwrap().to_string());
+    }
+    urls.sort();
+    assert_eq!(urls, vec![server_url.clone() + "admin", server_url + "login"]);
+}
+
+#[test]
+fn test_cli_json_reports_completion_without_hits() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/missing"))
+            .respond_with(responders::status_code(404)),
+    );
+
+    let wordlist_file = create_temp_wordlist("missing\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", &server.url("/").to_string(), "-w", wordlist_path, "--output-format", "json"])
+        .assert()
+        .success()
+        .stdout("{\"event\":\"completed\",\"hits\":0}\n")
+        .stderr(predicates::str::contains("# Completed with 0 hits."));
+}
+
 #[test]
 fn test_cli_status_code_filtering() {
     let wordlist_content = "found\nmoved\nforbidden\nnot_found\n";
@@ -550,6 +967,44 @@ fn test_cli_urls_file() {
     assert!(stdout_str.contains(&format!("[200 OK] {}file_test2 [0W, 0C, 0L]", server_url2)));
 }
 
+#[test]
+fn test_cli_output_per_host_writes_one_file_per_host() {
+    let servers = [Server::run(), Server::run()];
+    for (server, found) in servers.iter().zip(["/admin", "/login"]) {
+        for word in ["/admin", "/login"] {
+            let status = if word == found { 200 } else { 404 };
+            server.expect(
+                Expectation::matching(request::path(word))
+                    .respond_with(responders::status_code(status)),
+            );
+        }
+    }
+    let urls_content = format!("{}\n{}", servers[0].url("/"), servers[1].url("/"));
+    let urls_file = create_temp_urls_file(&urls_content);
+    let wordlist_file = create_temp_wordlist("admin\nlogin\n");
+    let dir = tempfile::tempdir().unwrap();
+    let hosts_dir = dir.path().join("hosts");
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["--urls-file", urls_file.path().to_str().unwrap()])
+        .args(["-w", wordlist_file.path().to_str().unwrap()])
+        .args(["--output-format", "csv", "--output-per-host"])
+        .arg(&hosts_dir)
+        .assert()
+        .success();
+
+    for (server, found) in servers.iter().zip(["admin", "login"]) {
+        let name = format!("127.0.0.1_{}.csv", server.addr().port());
+        let content = std::fs::read_to_string(hosts_dir.join(name)).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", content);
+        assert!(lines[0].starts_with("url,status"), "{}", content);
+        assert!(lines[1].starts_with(&format!("{}{}", server.url("/"), found)), "{}", content);
+    }
+    assert_eq!(std::fs::read_dir(&hosts_dir).unwrap().count(), 2);
+}
+
 #[test]
 fn test_cli_results_file() {
     let server1 = Server::run();
@@ -882,82 +1337,289 @@ fn test_cli_output_no_leading_whitespace() {
     }
 }
 
-mod start_scan_tests {
-    use bstr::{B, ByteSlice};
-    use dirnutek::{HttpMethod, FuzzMode, start_scan, ControlEvent}; // Added ControlEvent
-    use httptest::matchers::*;
-    use httptest::responders;
-    use httptest::{Expectation, Server};
-    use reqwest::Client;
-    use std::collections::{HashSet, HashMap};
-    use std::fs;
-    use std::path::PathBuf;
-    use std::sync::Arc;
-    use tokio::sync::{mpsc, Mutex, Semaphore, broadcast}; // Added broadcast
-    use url::Url;
-    use once_cell::sync::Lazy; // Added Lazy
-    use tokio::time::Duration; // Added this line
-
-// Global ControlEvent sender/receiver for testing
-static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>, tokio::sync::broadcast::Receiver<ControlEvent>)> =
-    Lazy::new(|| tokio::sync::broadcast::channel(1));
-
-    #[tokio::test]
-    async fn test_start_scan_no_recursion() {
-        let server = Server::run();
-        server.expect(
-            Expectation::matching(request::method_path("GET", "/admin/"))
-                .respond_with(responders::status_code(200)),
-        );
-        server.expect(
-            Expectation::matching(request::method_path("GET", "/test"))
-                .respond_with(responders::status_code(200)),
-        );
+#[test]
+fn test_cli_sort_output() {
+    let server = Server::run();
+    for (path, status, body) in [
+        ("/charlie", 200, "a"),
+        ("/alpha", 403, "abc"),
+        ("/bravo", 200, "ab"),
+    ] {
         server.expect(
-            Expectation::matching(request::method_path("GET", "/users"))
-                .respond_with(responders::status_code(200)),
+            Expectation::matching(request::method_path("GET", path))
+                .times(2)
+                .respond_with(responders::status_code(status).body(body)),
         );
+    }
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(1))
-            .redirect(reqwest::redirect::Policy::none())
-            .build()
-            .unwrap();
-        let base_url = Url::parse(&server.url("/").to_string()).unwrap();
-        let (tx, mut rx) = mpsc::channel(100);
-        let _semaphore = Arc::new(Semaphore::new(1));
-        let words = vec![
-            "admin/".to_string(),
-            "test".to_string(),
-            "users".to_string(),
-        ];
-        let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
+    let wordlist_file = create_temp_wordlist("charlie\nalpha\nbravo\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
 
-        let max_depth = 1; // max_depth = 1 (no recursion)
+    let found_order = |sort_by: &str| -> Vec<String> {
+        let output = Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args([
+                "-u",
+                &server_url,
+                "-w",
+                wordlist_path,
+                "-c",
+                "3",
+                "--sort-output",
+                "--sort-by",
+                sort_by,
+            ])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8_lossy(&output)
+            .lines()
+            .filter(|line| line.starts_with('['))
+            .map(|line| line.split(' ').find(|w| w.starts_with("http")).unwrap().to_string())
+            .collect()
+    };
+
+    let url = |word: &str| format!("{}{}", server_url, word);
+    assert_eq!(found_order("url"), [url("alpha"), url("bravo"), url("charlie")]);
+    assert_eq!(found_order("status"), [url("bravo"), url("charlie"), url("alpha")]);
+}
 
-            // This part will be declared globally once.
-        start_scan(
-            client,
+#[test]
+fn test_cli_warns_about_malformed_wordlist() {
+    let wordlist_file = create_temp_wordlist("admin\n<html>\nhttp://example.com/\nnot a word\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    // Nothing listens on the port, so every request fails fast.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let url = format!("http://{}/", addr);
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", &url, "-w", wordlist_path])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Warning: Only 25% of the wordlist lines look like words (3 of 4",
+        ))
+        .stderr(predicates::str::contains("e.g. '<html>'"));
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", &url, "-w", wordlist_path, "--no-wordlist-checks"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("wordlist lines").count(0));
+}
+
+#[test]
+fn test_cli_expands_env_vars_in_headers() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/secret")),
+            Box::new(request::headers(contains(("authorization", "Bearer s3cr3t")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let wordlist_file = create_temp_wordlist("secret\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .env("DIRNUTEK_TEST_TOKEN", "s3cr3t")
+        .args([
+            "-u",
+            &server_url,
+            "-w",
+            wordlist_path,
+            "-H",
+            "Authorization: Bearer ${DIRNUTEK_TEST_TOKEN}",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!("{}secret", server_url)));
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .env_remove("DIRNUTEK_TEST_UNSET")
+        .args([
+            "-u",
+            &server_url,
+            "-w",
+            wordlist_path,
+            "-H",
+            "Authorization: Bearer ${DIRNUTEK_TEST_UNSET}",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Environment variable 'DIRNUTEK_TEST_UNSET' is not set.",
+        ));
+}
+
+#[test]
+fn test_cli_start_at_resumes_wordlist() {
+    let server = Server::run();
+    for path in ["/bravo", "/charlie"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .times(2)
+                .respond_with(responders::status_code(200)),
+        );
+    }
+
+    let wordlist_file = create_temp_wordlist("alpha\nbravo\ncharlie\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+
+    for extra_args in [&["--start-at", "bravo"][..], &["--skip", "1", "--low-memory"][..]] {
+        Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(["-u", &server_url, "-w", wordlist_path])
+            .args(extra_args)
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("# Skipping the first 1 words."))
+            .stdout(predicates::str::contains("# Scan started with 2 words."))
+            .stdout(predicates::str::contains(format!("{}charlie", server_url)));
+    }
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", &server_url, "-w", wordlist_path, "--start-at", "delta"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Word 'delta' given to --start-at is not in the wordlist.",
+        ));
+}
+
+#[test]
+fn test_cli_reports_completion_without_hits() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/missing"))
+            .respond_with(responders::status_code(404)),
+    );
+
+    let wordlist_file = create_temp_wordlist("missing\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", &server_url, "-w", wordlist_path])
+        .assert()
+        .code(0)
+        .stdout(predicates::str::ends_with("# Completed with 0 hits.\n"));
+}
+
+#[test]
+fn test_cli_origin_and_referer_headers() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/api")),
+            Box::new(request::headers(contains(("origin", "https://api.evil.example")))),
+            Box::new(request::headers(contains(("referer", "https://override.example/")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let wordlist_file = create_temp_wordlist("api\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &server_url,
+            "-w",
+            wordlist_path,
+            "--origin",
+            "https://FUZZ.evil.example",
+            "--referer",
+            "https://referer.example/",
+            "-H",
+            "referer: https://override.example/",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!("{}api", server_url)));
+}
+
+mod start_scan_tests {
+    use bstr::{B, ByteSlice};
+    use dirnutek::{HttpMethod, FuzzMode, start_scan, ControlEvent, ScanConfig}; // Added ControlEvent
+    use httptest::matchers::*;
+    use httptest::responders;
+    use httptest::{Expectation, Server};
+    use reqwest::Client;
+    use std::collections::{HashSet, HashMap};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use tokio::sync::{mpsc, Mutex, Semaphore, broadcast}; // Added broadcast
+    use url::Url;
+    use once_cell::sync::Lazy; // Added Lazy
+    use tokio::time::Duration; // Added this line
+
+// Global ControlEvent sender/receiver for testing
+static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>, tokio::sync::broadcast::Receiver<ControlEvent>)> =
+    Lazy::new(|| tokio::sync::broadcast::channel(1));
+
+    #[tokio::test]
+    async fn test_start_scan_no_recursion() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/admin/"))
+                .respond_with(responders::status_code(200)),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/test"))
+                .respond_with(responders::status_code(200)),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/users"))
+                .respond_with(responders::status_code(200)),
+        );
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(1))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+        let _semaphore = Arc::new(Semaphore::new(1));
+        let words = vec![
+            "admin/".to_string(),
+            "test".to_string(),
+            "users".to_string(),
+        ];
+
+        let max_depth = 1; // max_depth = 1 (no recursion)
+
+            // This part will be declared globally once.
+        start_scan(
+            client,
             base_url,
             words,
             tx,
-            visited_urls.clone(),
-            TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-            1, // Concurrency for testing
-            HttpMethod::GET,
-            None, // exclude_status
-            None, // include_status
-            max_depth,
-            None, // delay
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            FuzzMode::Path,
-            vec![], // headers
-            None,   // data
+            TEST_CONTROL_CHANNEL.1.resubscribe(),
+            ScanConfig {
+                concurrency: 1,
+                max_depth,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -968,14 +1630,14 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
         }
 
         assert!(
-            received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s == &format!("[200 OK] {} [0W, 0C, 0L]", server.url("/admin/"))))
+            received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] {} [0W, 0C, 0L]", server.url("/admin/"))))
         );
         assert!(
-            received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s == &format!("[200 OK] {} [0W, 0C, 0L]", server.url("/test"))))
+            received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] {} [0W, 0C, 0L]", server.url("/test"))))
         );
         // Should not contain /admin/users as recursion depth is 1
         assert!(
-            !received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s == &format!("[200 OK] {}", server.url("/admin/users"))))
+            !received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] {}", server.url("/admin/users"))))
         );
     }
 
@@ -1012,26 +1674,16 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
             // This part will be declared globally once.
         start_scan(
             client,
-            base_url.clone(), // Clone base_url here
+            base_url.clone(),
             words,
             tx,
-            visited_urls.clone(),
-            TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-            1, // Concurrency for testing
-            HttpMethod::GET,
-            None,      // exclude_status
-            None,      // include_status
-            max_depth, // max_depth
-            None,      // delay
-            None,      // exact_words
-            None,      // exact_chars
-            None,      // exact_lines
-            None,      // exclude_exact_words
-            None,      // exclude_exact_chars
-            None,      // exclude_exact_lines
-            FuzzMode::Path,
-            vec![], // headers
-            None,   // data
+            TEST_CONTROL_CHANNEL.1.resubscribe(),
+            ScanConfig {
+                visited_urls: visited_urls.clone(),
+                concurrency: 1,
+                max_depth,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -1040,7 +1692,7 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
         // Consume all events until ScanFinished
         while let Some(msg) = rx.recv().await {
             if let dirnutek::ScanEvent::FoundUrl(s) = msg {
-                received_found_urls.push(s);
+                received_found_urls.push(s.to_string());
             }
         }
         // So, we expect messages for /, /a/, /a/a/  etc. up to max_depth
@@ -1113,7 +1765,6 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
         let (tx, mut rx) = mpsc::channel(100);
         let _semaphore = Arc::new(Semaphore::new(1));
         let words = vec!["word1".to_string(), "word2".to_string()];
-        let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
             // This part will be declared globally once.
         start_scan(
@@ -1121,23 +1772,12 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
             base_url,
             words,
             tx,
-            visited_urls.clone(),
-            TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-            1, // Concurrency for testing
-            HttpMethod::GET,
-            None, // exclude_status
-            None, // include_status
-            0,    // max_depth
-            None, // delay
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            FuzzMode::Subdomain,
-            vec![], // headers
-            None,   // data
+            TEST_CONTROL_CHANNEL.1.resubscribe(),
+            ScanConfig {
+                concurrency: 1,
+                fuzz_mode: FuzzMode::Subdomain,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -1148,10 +1788,10 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
         }
 
         assert!(
-            received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s == &format!("[200 OK] http://word1.example.com/ [0W, 0C, 0L]")))
+            received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] http://word1.example.com/ [0W, 0C, 0L]")))
         );
         assert!(
-            received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s == &format!("[200 OK] http://word2.example.com/ [0W, 0C, 0L]")))
+            received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string() == format!("[200 OK] http://word2.example.com/ [0W, 0C, 0L]")))
         );
     }
 
@@ -1176,7 +1816,6 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
         let (tx, mut rx) = mpsc::channel(100);
         let _semaphore = Arc::new(Semaphore::new(1));
         let words = vec!["word1".to_string(), "word2".to_string()];
-        let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
             // This part will be declared globally once.
         start_scan(
@@ -1184,23 +1823,12 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
             base_url,
             words,
             tx,
-            visited_urls.clone(),
-            TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-            1, // Concurrency for testing
-            HttpMethod::GET,
-            None, // exclude_status
-            None, // include_status
-            0,    // max_depth
-            None, // delay
-            None, // exact_words
-            None, // exact_chars
-            None, // exact_lines
-            None, // exclude_exact_words
-            None, // exclude_exact_chars
-            None, // exclude_exact_lines
-            FuzzMode::Parameter,
-            vec![], // headers
-            None,   // data
+            TEST_CONTROL_CHANNEL.1.resubscribe(),
+            ScanConfig {
+                concurrency: 1,
+                fuzz_mode: FuzzMode::Parameter,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -1210,11 +1838,11 @@ static TEST_CONTROL_CHANNEL: Lazy<(tokio::sync::broadcast::Sender<ControlEvent>,
             received_messages.push(msg);
         }
 
-        assert!(received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s == &format!(
+        assert!(received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string() == format!(
             "[200 OK] {}?param=word1 [0W, 0C, 0L]",
             server.url("/")
         ))));
-        assert!(received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s == &format!(
+        assert!(received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string() == format!(
             "[200 OK] {}?param=word2 [0W, 0C, 0L]",
             server.url("/")
         ))));
@@ -1260,3 +1888,1007 @@ fn test_cli_with_custom_headers() {
         .success()
         .stdout(predicates::str::contains("[200 OK]"));
 }
+
+// Spawns dirnutek and returns the peak resident set size of that child in KiB.
+#[cfg(target_os = "linux")]
+#[allow(clippy::zombie_processes)] // The child is reaped by `wait4` below.
+fn peak_rss_kib(args: &[&str]) -> i64 {
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("dirnutek"))
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("Failed to spawn dirnutek");
+    let mut status = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let pid = unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut usage) };
+    assert_eq!(pid, child.id() as libc::pid_t, "wait4 failed");
+    usage.ru_maxrss
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_low_memory_bounded_rss() {
+    // Two wordlists with the same number of words: one tiny, one ~64 MiB.
+    let long_word = "a".repeat(32 * 1024);
+    let mut small_content = String::new();
+    let mut large_content = String::new();
+    for i in 0..2000 {
+        small_content.push_str(&format!("{}\n", i));
+        large_content.push_str(&format!("{}{}\n", i, long_word));
+    }
+    let small_wordlist = create_temp_wordlist(&small_content);
+    let large_wordlist = create_temp_wordlist(&large_content);
+
+    // Nothing listens on the port, so every request fails fast.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let url = format!("http://{}/", addr);
+
+    let small_rss = peak_rss_kib(&[
+        "-u",
+        &url,
+        "-w",
+        small_wordlist.path().to_str().unwrap(),
+        "--concurrency",
+        "10",
+        "--low-memory",
+    ]);
+    let large_rss = peak_rss_kib(&[
+        "-u",
+        &url,
+        "-w",
+        large_wordlist.path().to_str().unwrap(),
+        "--concurrency",
+        "10",
+        "--low-memory",
+    ]);
+
+    // The large wordlist must not be held in memory.
+    assert!(
+        large_rss - small_rss < 16 * 1024,
+        "Peak RSS grew from {} KiB to {} KiB with a 64 MiB wordlist",
+        small_rss,
+        large_rss
+    );
+}
+
+#[test]
+fn test_cli_preview_prints_plan_without_scanning() {
+    let wordlist_file = create_temp_wordlist("admin\nlogin.php\nbackup\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    // Nothing listens on the target; --preview must not send any request.
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            "http://127.0.0.1:9/",
+            "-u",
+            "http://FUZZ.localhost:9/",
+            "-w",
+            wordlist_path,
+            "--extension-bypass",
+            "--exclude-status",
+            "403,500",
+            "--delay",
+            "50",
+            "--preview",
+        ])
+        .assert()
+        .code(0)
+        .stdout(predicates::str::contains("#   Targets: 2"))
+        .stdout(predicates::str::contains("(Path fuzzing, 7 words)"))
+        .stdout(predicates::str::contains("(Subdomain fuzzing, 3 words)"))
+        .stdout(predicates::str::contains("#   Estimated requests: 10 (first level only)"))
+        .stdout(predicates::str::contains("#   Delay: 50 ms"))
+        .stdout(predicates::str::contains("#   Filters: exclude status 403,500"))
+        .stdout(predicates::str::contains("Scan started").count(0));
+}
+
+#[test]
+fn test_cli_writes_har_archive() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .times(2)
+            .respond_with(responders::status_code(200).body("admin panel")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/missing"))
+            .times(2)
+            .respond_with(responders::status_code(404)),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\nmissing\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+    let har_dir = tempfile::tempdir().unwrap();
+
+    for (hits_only, expected_entries) in [(false, 2), (true, 1)] {
+        let har_path = har_dir.path().join(format!("scan-{}.har", hits_only));
+        let mut args = vec![
+            "-u".to_string(),
+            server_url.clone(),
+            "-w".to_string(),
+            wordlist_path.to_string(),
+            "-c".to_string(),
+            "1".to_string(),
+            "--har".to_string(),
+            har_path.to_str().unwrap().to_string(),
+        ];
+        if hits_only {
+            args.push("--har-hits-only".to_string());
+        }
+        Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(&args)
+            .assert()
+            .code(0)
+            .stdout(predicates::str::contains(format!(
+                "# Wrote {} requests to",
+                expected_entries
+            )));
+
+        let har = std::fs::read_to_string(&har_path).unwrap();
+        assert!(har.contains("\"version\": \"1.2\""), "{}", har);
+        assert_eq!(har.matches("\"startedDateTime\"").count(), expected_entries, "{}", har);
+        assert!(har.contains(&format!("\"url\": \"{}admin\"", server_url)), "{}", har);
+        assert!(har.contains("\"text\": \"admin panel\""), "{}", har);
+        assert_eq!(har.contains("\"status\": 404"), !hits_only, "{}", har);
+    }
+}
+
+#[test]
+fn test_cli_skip_dead_hosts() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(200)),
+    );
+
+    // Reserve a port that nothing listens on.
+    let dead_url = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/", listener.local_addr().unwrap())
+    };
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let server_url = server.url("/").to_string();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &dead_url,
+            "-u",
+            &server_url,
+            "-w",
+            wordlist_path,
+            "--skip-dead-hosts",
+        ])
+        .assert()
+        .code(0)
+        .stdout(predicates::str::contains(format!("{}admin", server_url)))
+        .stdout(predicates::str::contains(format!(
+            "# Skipped unreachable hosts (1): {}",
+            dead_url
+        )))
+        .stdout(predicates::str::contains("# Completed with 1 hits."));
+}
+
+#[test]
+fn test_cli_shuffle_targets_is_reproducible_with_seed() {
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let targets: Vec<String> = (1..=8).map(|i| format!("http://host{}.invalid/", i)).collect();
+
+    let plan = |seed: &str| {
+        let mut args = vec!["-w", wordlist_path, "--preview", "--shuffle-targets", "--target-seed", seed];
+        for target in &targets {
+            args.extend(["-u", target.as_str()]);
+        }
+        let output = Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(&args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(&format!("# Shuffled targets with seed {}.", seed)));
+        stdout
+            .lines()
+            .filter(|line| line.contains(".invalid/"))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    let first = plan("42");
+    assert_eq!(first.len(), targets.len());
+    assert_eq!(first, plan("42"));
+    // With 8! orders, another seed practically never yields the same one.
+    assert_ne!(first, plan("43"));
+}
+
+#[test]
+fn test_cli_prints_the_seed_that_repeats_the_shuffle() {
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let targets: Vec<String> = (1..=8).map(|i| format!("http://host{}.invalid/", i)).collect();
+
+    let plan = |seed: Option<&str>| {
+        let mut args = vec!["-w", wordlist_path, "--preview", "--shuffle-targets"];
+        args.extend(seed.map(|seed| ["--seed", seed]).into_iter().flatten());
+        for target in &targets {
+            args.extend(["-u", target.as_str()]);
+        }
+        let output = Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(&args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let seed = stdout
+            .lines()
+            .find_map(|line| line.strip_prefix("# Random seed: "))
+            .and_then(|line| line.split_once(' '))
+            .map(|(seed, _)| seed.to_string())
+            .expect("the seed is printed");
+        let order = stdout
+            .lines()
+            .filter(|line| line.contains(".invalid/"))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        (seed, order)
+    };
+
+    let (seed, order) = plan(None);
+    assert_eq!(plan(Some(&seed)), (seed.clone(), order));
+}
+
+/// Serves `200 OK` with keep-alive on a raw socket and counts the accepted connections.
+fn spawn_counting_server() -> (String, Arc<AtomicUsize>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { break };
+            counter.fetch_add(1, Ordering::SeqCst);
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut writer = stream;
+                loop {
+                    // Read one request head, then answer it.
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        match reader.read_line(&mut line) {
+                            Ok(0) | Err(_) => return,
+                            Ok(_) if line == "\r\n" => break,
+                            Ok(_) => {}
+                        }
+                    }
+                    let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                    if writer.write_all(response.as_bytes()).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    (url, connections)
+}
+
+#[test]
+fn test_cli_no_pipelining_opens_a_connection_per_request() {
+    let wordlist_file = create_temp_wordlist("one\ntwo\nthree\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    for (no_pipelining, expected_connections) in [(false, 1), (true, 3)] {
+        let (server_url, connections) = spawn_counting_server();
+        let mut args = vec!["-u", &server_url, "-w", wordlist_path, "-c", "1"];
+        if no_pipelining {
+            args.push("--no-pipelining");
+        }
+        Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(&args)
+            .assert()
+            .code(0)
+            .stdout(predicates::str::contains("# Completed with 3 hits."));
+        assert_eq!(connections.load(Ordering::SeqCst), expected_connections);
+    }
+}
+
+#[test]
+fn test_cli_diff_subcommand() {
+    let old = create_temp_wordlist(
+        "# Starting scan for URL: http://example.com/ (FuzzMode: Path)\n\
+         [200 OK] http://example.com/admin [1W, 10C, 1L]\n\
+         [200 OK] http://example.com/backup [1W, 10C, 1L]\n",
+    );
+    let new = create_temp_wordlist(
+        "[200 OK] http://example.com/admin [1W, 10C, 1L]\n\
+         [200 OK] http://example.com/login [1W, 10C, 1L]\n",
+    );
+
+    // No --wordlist is needed for subcommands.
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["diff", old.path().to_str().unwrap(), new.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(
+            "+ [200 OK] http://example.com/login [1W, 10C, 1L]\n\
+             - [200 OK] http://example.com/backup [1W, 10C, 1L]\n\
+             # 1 added, 1 removed, 0 changed.\n",
+        );
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["diff", "--json", old.path().to_str().unwrap(), new.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"added\": ["))
+        .stdout(predicates::str::contains("\"url\": \"http://example.com/login\""));
+}
+
+#[test]
+fn test_cli_diff_subcommand_reads_json_results() {
+    // As written by --output-format json.
+    let old = create_temp_wordlist(concat!(
+        "{\"url\":\"http://example.com/admin\",\"status\":200,\"redirect_target\":null,\"words\":1,\"chars\":10,\"lines\":1,\"method\":\"GET\",\"timestamp\":\"2026-10-16T00:00:00Z\",\"classification\":\"found\"}\n",
+        "{\"url\":\"http://example.com/backup\",\"status\":200,\"redirect_target\":null,\"words\":1,\"chars\":10,\"lines\":1,\"method\":\"GET\",\"timestamp\":\"2026-10-16T00:00:00Z\",\"classification\":\"found\"}\n",
+        "{\"event\":\"completed\",\"hits\":2}\n",
+    ));
+    let new = create_temp_wordlist(concat!(
+        "{\"url\":\"http://example.com/admin\",\"status\":403,\"redirect_target\":null,\"words\":1,\"chars\":9,\"lines\":1,\"method\":\"GET\",\"timestamp\":\"2026-10-17T00:00:00Z\",\"classification\":\"protected\"}\n",
+        "{\"url\":\"http://example.com/login\",\"status\":200,\"redirect_target\":null,\"words\":2,\"chars\":20,\"lines\":1,\"method\":\"GET\",\"timestamp\":\"2026-10-17T00:00:00Z\",\"classification\":\"found\"}\n",
+        "{\"event\":\"completed\",\"hits\":2}\n",
+    ));
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["diff", old.path().to_str().unwrap(), new.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(
+            "+ [200 OK] http://example.com/login [2W, 20C, 1L]\n\
+             - [200 OK] http://example.com/backup [1W, 10C, 1L]\n\
+             ~ http://example.com/admin (status 200 -> 403, 10C -> 9C)\n\
+             # 1 added, 1 removed, 1 changed.\n",
+        );
+}
+
+#[test]
+fn test_cli_wordlist_clean() {
+    let wordlist_file = create_temp_wordlist("# comment\nadmin\n\n  login  \nadmin\nLogin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["wordlist", "clean", wordlist_path])
+        .assert()
+        .success()
+        .stdout("admin\nlogin\nLogin\n")
+        .stderr(predicates::str::contains("Kept 3 words; removed 1 comments and 1 duplicates"));
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["wordlist", "clean", "--lowercase", "--in-place", wordlist_path])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(std::fs::read_to_string(wordlist_path).unwrap(), "admin\nlogin\n");
+}
+
+#[test]
+fn test_cli_passive_refilters_stored_results() {
+    let results_file = create_temp_wordlist(concat!(
+        "# Scanning http://example.com/\n",
+        "[200 OK] http://example.com/admin [5W, 20C, 1L]\n",
+        "[403 Forbidden] http://example.com/secret [2W, 9C, 1L]\n",
+        "{\"url\":\"http://example.com/login\",\"status\":200,\"words\":40,\"chars\":300,\"lines\":12}\n",
+        "# Completed with 3 hits.\n",
+        "{\"event\":\"completed\",\"hits\":3}\n",
+    ));
+    let results_path = results_file.path().to_str().unwrap();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["--passive", results_path, "--include-status", "200", "--max-words", "10"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    let results: Vec<&str> = stdout_str.lines().filter(|line| !line.starts_with('#')).collect();
+    assert_eq!(results, vec!["[200 OK] http://example.com/admin [5W, 20C, 1L]"]);
+    assert!(stdout_str.contains("# Kept 1 of 3 results from"));
+}
+
+#[test]
+fn test_cli_passive_keeps_unknown_sizes() {
+    let results_file = create_temp_wordlist(concat!(
+        "[200 OK] http://example.com/admin [?W, ?C, ?L]\n",
+        "{\"url\":\"http://example.com/login\",\"status\":200,\"words\":null,\"chars\":null,\"lines\":null}\n",
+    ));
+    let results_path = results_file.path().to_str().unwrap();
+
+    let refilter = |extra_args: &[&str]| {
+        let output = Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(["--passive", results_path])
+            .args(extra_args)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8_lossy(&output)
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        refilter(&[]),
+        vec![
+            "[200 OK] http://example.com/admin [?W, ?C, ?L]",
+            "[200 OK] http://example.com/login [?W, ?C, ?L]",
+        ]
+    );
+    // An unknown size matches no size filter.
+    assert!(refilter(&["--max-words", "10"]).is_empty());
+}
+
+#[test]
+fn test_cli_proxy_routes_requests_with_credentials() {
+    let proxy = Server::run();
+    proxy.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::path(matches("/admin$"))),
+            Box::new(request::headers(contains((
+                "proxy-authorization",
+                "Basic dXNlcjpzZWNyZXQ=",
+            )))),
+        ]))
+        .respond_with(responders::status_code(200).body("proxied")),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let proxy_url = format!("http://user:secret@{}", proxy.addr());
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", "http://target.invalid/", "-w", wordlist_path, "--proxy", &proxy_url])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    assert!(
+        stdout_str.contains("[200 OK] http://target.invalid/admin [1W, 7C, 1L]"),
+        "{}",
+        stdout_str
+    );
+}
+
+#[test]
+fn test_cli_invalid_proxy_fails_before_scanning() {
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", "http://127.0.0.1:1/", "-w", wordlist_path, "--proxy", "ftp://proxy:21"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid proxy URL 'ftp://proxy:21'"));
+}
+
+#[test]
+fn test_cli_count_only_prints_totals_per_status() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/login"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/secret"))
+            .respond_with(responders::status_code(403)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/missing"))
+            .respond_with(responders::status_code(404)),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\nlogin\nsecret\nmissing\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let output_file = tempfile::NamedTempFile::new().unwrap();
+    let output_path = output_file.path().to_str().unwrap();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &server.url("/").to_string(),
+            "-w",
+            wordlist_path,
+            "--count-only",
+            "-o",
+            output_path,
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    assert!(stdout_str.lines().all(|line| line.starts_with('#')), "{}", stdout_str);
+    assert!(stdout_str.contains("# Hits by status: 200: 2, 403: 1\n"), "{}", stdout_str);
+    assert!(stdout_str.contains("# Requests sent: 4.\n"), "{}", stdout_str);
+    assert_eq!(std::fs::read_to_string(output_path).unwrap().lines().count(), 3);
+}
+
+#[test]
+fn test_cli_follow_redirects_reports_final_status_and_count() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/old"))
+            .respond_with(responders::status_code(301).insert_header("Location", "/new")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/new"))
+            .respond_with(responders::status_code(200).body("moved here")),
+    );
+
+    let wordlist_file = create_temp_wordlist("old\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &server.url("/").to_string(),
+            "-w",
+            wordlist_path,
+            "--follow-redirects",
+            "--max-redirects",
+            "3",
+            "--output-format",
+            "json",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    let line = stdout_str.lines().find(|line| line.starts_with('{')).unwrap();
+    let finding: serde_json::Value = serde_json::from_str(line).unwrap();
+    assert_eq!(finding["status"], 200);
+    assert_eq!(finding["redirects"], 1);
+}
+
+#[test]
+fn test_cli_max_redirects_requires_follow_redirects() {
+    let wordlist_file = create_temp_wordlist("old\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", "http://127.0.0.1:1/", "-w", wordlist_path, "--max-redirects", "3"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--follow-redirects"));
+}
+
+#[test]
+fn test_cli_from_browser_reuses_curl_headers_and_cookies() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/account")),
+            Box::new(request::headers(contains(("cookie", "session=abc; theme=dark")))),
+            Box::new(request::headers(contains(("x-csrf-token", "override")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let wordlist_file = create_temp_wordlist("account\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let curl_file = create_temp_wordlist(
+        "curl 'https://app.example/' \\\n  -H 'x-csrf-token: from-browser' \\\n  -b 'session=abc; theme=dark'\n",
+    );
+    let curl_path = curl_file.path().to_str().unwrap();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &server.url("/").to_string(),
+            "-w",
+            wordlist_path,
+            "--from-browser",
+            curl_path,
+            "-H",
+            "X-CSRF-Token: override",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    assert!(stdout_str.contains("[200 OK]"), "{}", stdout_str);
+}
+
+#[test]
+fn test_cli_max_hits_per_status_suppresses_noisy_status() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::path(matches("^/denied")))
+            .times(4)
+            .respond_with(responders::status_code(403)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(200)),
+    );
+
+    let wordlist_file = create_temp_wordlist("denied1\ndenied2\ndenied3\ndenied4\nadmin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &server.url("/").to_string(),
+            "-w",
+            wordlist_path,
+            "-c",
+            "1",
+            "--max-hits-per-status",
+            "403=1",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    assert_eq!(stdout_str.matches("[403 Forbidden]").count(), 1, "{}", stdout_str);
+    assert!(stdout_str.contains("[200 OK]"), "{}", stdout_str);
+    assert!(
+        stdout_str.contains("# Suppressed 3 more hits with status 403 (--max-hits-per-status)."),
+        "{}",
+        stdout_str
+    );
+    assert!(stdout_str.contains("# Completed with 2 hits."), "{}", stdout_str);
+}
+
+#[test]
+fn test_cli_charset_generates_words_without_wordlist() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/ab"))
+            .respond_with(responders::status_code(200)),
+    );
+    for path in ["/a", "/b", "/aa", "/ba", "/bb"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(404)),
+        );
+    }
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", &server.url("/").to_string(), "--charset", "ab", "--length", "1-2"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    assert!(
+        stdout_str.contains("# Generating 6 words from charset 'ab' with length 1-2."),
+        "{}",
+        stdout_str
+    );
+    assert!(stdout_str.contains("# Scan started with 6 words."), "{}", stdout_str);
+    assert!(stdout_str.contains(&format!("[200 OK] {}", server.url("/ab"))), "{}", stdout_str);
+}
+
+#[test]
+fn test_cli_keyword_replaces_only_the_custom_keyword() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/FUZZ/search")),
+            Box::new(request::query(matches("^id=42&q=Z$"))),
+            Box::new(request::headers(contains(("x-token", "42-Z")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let wordlist_file = create_temp_wordlist("42\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &server.url("/FUZZ/search?id=ZZ&q=Z").to_string(),
+            "-w",
+            wordlist_path,
+            "--keyword",
+            "ZZ",
+            "-H",
+            "X-Token: ZZ-Z",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    assert!(stdout_str.contains("[200 OK]"), "{}", stdout_str);
+    assert!(stdout_str.contains("# Completed with 1 hits."), "{}", stdout_str);
+}
+
+#[test]
+fn test_cli_resolve_pins_hosts_across_redirects() {
+    let server = Server::run();
+    let port = server.addr().port();
+    let host_a = format!("a.dirnutek.test:{}", port);
+    let host_b = format!("b.dirnutek.test:{}", port);
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/admin")),
+            Box::new(request::headers(contains(("host", host_a.clone())))),
+        ]))
+        .respond_with(
+            responders::status_code(302)
+                .insert_header("Location", format!("http://{}/admin", host_b)),
+        ),
+    );
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/admin")),
+            Box::new(request::headers(contains(("host", host_b.clone())))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &format!("http://{}/", host_a),
+            "-w",
+            wordlist_path,
+            "--resolve",
+            "*.dirnutek.test:127.0.0.1",
+            "--follow-redirects",
+            "-H",
+            &format!("Host: {}", host_a),
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    assert!(
+        stdout_str.contains(&format!("[200 OK] http://{}/admin", host_a)),
+        "{}",
+        stdout_str
+    );
+    assert!(stdout_str.contains("# Completed with 1 hits."), "{}", stdout_str);
+}
+
+#[test]
+fn test_cli_urls_only_prints_bare_urls() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .times(2)
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/old"))
+            .times(2)
+            .respond_with(responders::status_code(301).insert_header("Location", "/new")),
+    );
+
+    let wordlist_file = create_temp_wordlist("admin\nold\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let scan = |extra: &[&str]| {
+        let output = Command::cargo_bin("dirnutek")
+            .expect("Failed to find dircrab binary")
+            .args(["-u", &server.url("/").to_string(), "-w", wordlist_path, "-c", "1"])
+            .args(["--sort-output", "--urls-only"])
+            .args(extra)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let stdout_str = String::from_utf8_lossy(&output).to_string();
+        stdout_str
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        scan(&[]),
+        [server.url("/admin").to_string(), server.url("/old").to_string()]
+    );
+    assert_eq!(
+        scan(&["--include-redirect-urls"]),
+        [
+            server.url("/admin").to_string(),
+            server.url("/old").to_string(),
+            server.url("/new").to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_cli_one_per_dir_reports_first_hit_and_keeps_all_in_full_output() {
+    let server = Server::run();
+    for path in ["/index.php", "/login.php", "/admin/"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200)),
+        );
+    }
+
+    let wordlist_file = create_temp_wordlist("index.php\nlogin.php\nadmin/\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+    let output_file = tempfile::NamedTempFile::new().unwrap();
+
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &server.url("/").to_string(),
+            "-w",
+            wordlist_path,
+            "-c",
+            "1",
+            "--one-per-dir",
+            "--full-output",
+            "-o",
+            output_file.path().to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let stdout_str = String::from_utf8_lossy(&cmd_output);
+    assert_eq!(stdout_str.matches("[200 OK]").count(), 1, "{}", stdout_str);
+    assert!(stdout_str.contains("/index.php"), "{}", stdout_str);
+    assert!(
+        stdout_str.contains("# Suppressed 2 more hits in directories that already had one (--one-per-dir)."),
+        "{}",
+        stdout_str
+    );
+    assert!(stdout_str.contains("# Completed with 1 hits."), "{}", stdout_str);
+
+    let written = std::fs::read_to_string(output_file.path()).unwrap();
+    assert_eq!(written.matches("[200 OK]").count(), 3, "{}", written);
+}
+
+#[test]
+fn test_cli_timeout_aborts_requests_to_a_hanging_server() {
+    // Accepts connections but never answers.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let mut sockets = Vec::new();
+        for socket in listener.incoming() {
+            sockets.push(socket);
+        }
+    });
+
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    let started = std::time::Instant::now();
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            &format!("http://{}/", addr),
+            "-w",
+            wordlist_path,
+            "--timeout",
+            "1",
+            "--verbose",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    let stdout_str = String::from_utf8_lossy(&cmd_output.stdout);
+    let stderr_str = String::from_utf8_lossy(&cmd_output.stderr);
+    assert!(stderr_str.contains("timeout: "), "{}", stderr_str);
+    assert!(stdout_str.contains("# Completed with 0 hits."), "{}", stdout_str);
+}
+
+#[test]
+fn test_cli_connect_timeout_fails_fast_on_unroutable_host() {
+    let wordlist_file = create_temp_wordlist("admin\n");
+    let wordlist_path = wordlist_file.path().to_str().unwrap();
+
+    let started = std::time::Instant::now();
+    let cmd_output = Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args([
+            "-u",
+            "http://10.255.255.1/",
+            "-w",
+            wordlist_path,
+            "--connect-timeout",
+            "300",
+            "--verbose",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+
+    // Without the connect timeout, the request would wait for the 10 second timeout.
+    assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    let stderr_str = String::from_utf8_lossy(&cmd_output.stderr);
+    // Depending on the network, the connect either times out or is refused outright.
+    assert!(stderr_str.contains("http://10.255.255.1/admin"), "{}", stderr_str);
+}
+
+#[test]
+fn test_cli_connect_timeout_rejects_zero() {
+    Command::cargo_bin("dirnutek")
+        .expect("Failed to find dircrab binary")
+        .args(["-u", "http://127.0.0.1/", "--connect-timeout", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("The connect timeout must be greater than 0."));
+}
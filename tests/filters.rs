@@ -1,11 +1,14 @@
-use dirnutek::{FuzzMode, HttpMethod, start_scan, ControlEvent}; // Added ControlEvent
+use dirnutek::{FuzzMode, HttpMethod, start_scan, ControlEvent, ScanConfig}; // Added ControlEvent
+use flate2::Compression;
+use flate2::write::GzEncoder;
 use httptest::responders;
 use httptest::{Expectation, Server, matchers::*};
 use reqwest::Client;
 use std::collections::HashSet;
+use std::io::Write;
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::Semaphore;
 use url::Url;
 use once_cell::sync::Lazy; // Added this line
 
@@ -30,7 +33,6 @@ async fn test_filter_by_exact_word_count() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["three_words".to_string(), "four_words".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     // This part will be declared globally once.
 
@@ -39,23 +41,12 @@ async fn test_filter_by_exact_word_count() {
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1, // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        Some(vec![3]), // exact_words
-        None,          // exact_chars
-        None,          // exact_lines
-        None,          // exclude_exact_words
-        None,          // exclude_exact_chars
-        None,          // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exact_words: Some(vec![3]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -68,12 +59,12 @@ async fn test_filter_by_exact_word_count() {
     assert!(
         received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_words")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_words")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("four_words")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("four_words")))
     );
 }
 
@@ -94,7 +85,6 @@ async fn test_filter_by_exact_word_count_no_match() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["three_words".to_string(), "four_words".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     // This part will be declared globally once.
 
@@ -103,23 +93,12 @@ async fn test_filter_by_exact_word_count_no_match() {
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1, // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        Some(vec![5]), // exact_words (no match for 3 or 4 words)
-        None,          // exact_chars
-        None,          // exact_lines
-        None,          // exclude_exact_words
-        None,          // exclude_exact_chars
-        None,          // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exact_words: Some(vec![5]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -132,12 +111,12 @@ async fn test_filter_by_exact_word_count_no_match() {
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_words")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_words")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("four_words")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("four_words")))
     );
 }
 
@@ -158,7 +137,6 @@ async fn test_filter_by_exact_char_count() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["three_chars".to_string(), "four_chars".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     // This part will be declared globally once.
 
@@ -167,23 +145,12 @@ async fn test_filter_by_exact_char_count() {
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1, // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        None,          // exact_words
-        Some(vec![3]), // exact_chars
-        None,          // exact_lines
-        None,          // exclude_exact_words
-        None,          // exclude_exact_chars
-        None,          // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exact_chars: Some(vec![3]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -196,12 +163,12 @@ async fn test_filter_by_exact_char_count() {
     assert!(
         received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_chars")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_chars")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("four_chars")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("four_chars")))
     );
 }
 
@@ -222,7 +189,6 @@ async fn test_filter_by_exact_char_count_no_match() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["three_chars".to_string(), "four_chars".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     // This part will be declared globally once.
 
@@ -231,23 +197,12 @@ async fn test_filter_by_exact_char_count_no_match() {
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1, // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        None,          // exact_words
-        Some(vec![5]), // exact_chars (no match for 3 or 4 chars)
-        None,          // exact_lines
-        None,          // exclude_exact_words
-        None,          // exclude_exact_chars
-        None,          // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exact_chars: Some(vec![5]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -260,12 +215,12 @@ async fn test_filter_by_exact_char_count_no_match() {
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_chars")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_chars")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("four_chars")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("four_chars")))
     );
 }
 
@@ -286,7 +241,6 @@ async fn test_filter_by_exact_line_count() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["two_lines".to_string(), "three_lines".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     // This part will be declared globally once.
 
@@ -295,23 +249,12 @@ async fn test_filter_by_exact_line_count() {
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1, // Concurrency for testing
-        HttpMethod::GET,
-        None,          // exclude_status
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        None,          // exact_words
-        None,          // exact_chars
-        Some(vec![2]), // exact_lines
-        None,          // exclude_exact_words
-        None,          // exclude_exact_chars
-        None,          // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exact_lines: Some(vec![2]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -324,12 +267,12 @@ async fn test_filter_by_exact_line_count() {
     assert!(
         received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("two_lines")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("two_lines")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_lines")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_lines")))
     );
 }
 
@@ -350,30 +293,18 @@ async fn test_filter_by_exact_line_count_no_match() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["two_lines".to_string(), "three_lines".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     start_scan(
         client,
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1,               // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        None,          // exact_words
-        None,          // exact_chars
-        Some(vec![5]), // exact_lines (no match for 2 or 3 lines)
-        None,          // exclude_exact_words
-        None,          // exclude_exact_chars
-        None,          // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exact_lines: Some(vec![5]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -386,12 +317,12 @@ async fn test_filter_by_exact_line_count_no_match() {
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("two_lines")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("two_lines")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_lines")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_lines")))
     );
 }
 
@@ -425,30 +356,20 @@ async fn test_filter_by_exact_combined() {
         "no_match_chars".to_string(),
         "no_match_lines".to_string(),
     ];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     start_scan(
         client,
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1,               // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,           // include_status
-        0,              // max_depth
-        None,           // delay
-        Some(vec![6]),  // exact_words
-        Some(vec![27]), // exact_chars
-        Some(vec![3]),  // exact_lines
-        None,           // exclude_exact_words
-        None,           // exclude_exact_chars
-        None,           // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exact_words: Some(vec![6]),
+            exact_chars: Some(vec![27]),
+            exact_lines: Some(vec![3]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -458,21 +379,21 @@ async fn test_filter_by_exact_combined() {
         received_messages.push(msg);
     }
 
-    assert!(received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("match"))));
+    assert!(received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("match"))));
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("no_match_words")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("no_match_words")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("no_match_chars")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("no_match_chars")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("no_match_lines")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("no_match_lines")))
     );
 }
 
@@ -493,30 +414,18 @@ async fn test_filter_by_exclude_exact_word_count() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["three_words".to_string(), "four_words".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     start_scan(
         client,
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1,               // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        None,          // exact_words
-        None,          // exact_chars
-        None,          // exact_lines
-        Some(vec![3]), // exclude_exact_words (should exclude "three_words")
-        None,          // exclude_exact_chars
-        None,          // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exclude_exact_words: Some(vec![3]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -529,12 +438,12 @@ async fn test_filter_by_exclude_exact_word_count() {
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_words")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_words")))
     );
     assert!(
         received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("four_words")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("four_words")))
     );
 }
 
@@ -555,30 +464,18 @@ async fn test_filter_by_exclude_exact_char_count() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["three_chars".to_string(), "four_chars".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     start_scan(
         client,
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1,               // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        None,          // exact_words
-        None,          // exact_chars
-        None,          // exact_lines
-        None,          // exclude_exact_words
-        Some(vec![3]), // exclude_exact_chars (should exclude "three_chars")
-        None,          // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exclude_exact_chars: Some(vec![3]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -591,12 +488,12 @@ async fn test_filter_by_exclude_exact_char_count() {
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_chars")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_chars")))
     );
     assert!(
         received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("four_chars")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("four_chars")))
     );
 }
 
@@ -617,30 +514,18 @@ async fn test_filter_by_exclude_exact_line_count() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["two_lines".to_string(), "three_lines".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     start_scan(
         client,
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1,               // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        None,          // exact_words
-        None,          // exact_chars
-        None,          // exact_lines
-        None,          // exclude_exact_words
-        None,          // exclude_exact_chars
-        Some(vec![2]), // exclude_exact_lines (should exclude "two_lines")
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exclude_exact_lines: Some(vec![2]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -653,12 +538,12 @@ async fn test_filter_by_exclude_exact_line_count() {
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("two_lines")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("two_lines")))
     );
     assert!(
         received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("three_lines")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("three_lines")))
     );
 }
 
@@ -692,30 +577,20 @@ async fn test_filter_by_exclude_exact_combined() {
         "exclude_chars".to_string(),
         "exclude_lines".to_string(),
     ];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
 
     start_scan(
         client,
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1,               // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,          // include_status
-        0,             // max_depth
-        None,          // delay
-        None,          // exact_words
-        None,          // exact_chars
-        None,          // exact_lines
-        Some(vec![3]), // exclude_exact_words
-        Some(vec![3]), // exclude_exact_chars
-        Some(vec![2]), // exclude_exact_lines
-        FuzzMode::Path,
-        vec![],
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exclude_exact_words: Some(vec![3]),
+            exclude_exact_chars: Some(vec![3]),
+            exclude_exact_lines: Some(vec![2]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -728,22 +603,22 @@ async fn test_filter_by_exclude_exact_combined() {
     assert!(
         received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("match_all")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("match_all")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("exclude_words")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("exclude_words")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("exclude_chars")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("exclude_chars")))
     );
     assert!(
         !received_messages
             .iter()
-            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("exclude_lines")))
+            .any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("exclude_lines")))
     );
 }
 
@@ -760,7 +635,6 @@ async fn test_start_scan_with_custom_headers() {
     let (tx, mut rx) = mpsc::channel(100);
     let _semaphore = Arc::new(Semaphore::new(1));
     let words = vec!["test".to_string()];
-    let visited_urls: Arc<Mutex<HashSet<url::Url>>> = Arc::new(Mutex::new(HashSet::new()));
     let headers = vec!["X-Custom-Header: test".to_string()];
 
     start_scan(
@@ -768,23 +642,210 @@ async fn test_start_scan_with_custom_headers() {
         base_url,
         words,
         tx,
-        visited_urls.clone(),
-        TEST_CONTROL_CHANNEL.1.resubscribe(), // Dummy receiver for control events
-        1,               // Concurrency for testing
-        HttpMethod::GET,
-        None,
-        None,
-        0,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        FuzzMode::Path,
-        headers,
-        None, // data
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            headers,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut received_messages = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        received_messages.push(msg);
+    }
+
+    assert!(received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().contains("[200 OK]"))));
+}
+
+#[tokio::test]
+async fn test_max_response_size_truncates_body() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/long"))
+            .respond_with(responders::status_code(200).body("one two three four")),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+    let words = vec!["long".to_string()];
+
+    start_scan(
+        client,
+        base_url,
+        words,
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exact_words: Some(vec![2]),
+            max_response_size: Some(7),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut received_messages = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        received_messages.push(msg);
+    }
+
+    assert!(received_messages.iter().any(
+        |e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.to_string().ends_with("long [2W, 7C, 1L]"))
+    ));
+}
+
+#[tokio::test]
+async fn test_low_memory_streams_wordlist_from_disk() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/streamed"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/also_streamed"))
+            .respond_with(responders::status_code(200)),
+    );
+
+    let mut wordlist = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut wordlist, b"streamed\n\nalso_streamed\n").unwrap();
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec![],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            wordlist_path: Some(wordlist.path().to_path_buf()),
+            low_memory: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut received_messages = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        received_messages.push(msg);
+    }
+
+    assert!(received_messages
+        .iter()
+        .any(|e| matches!(e, dirnutek::ScanEvent::ScanStarted { total_words: 2 })));
+    assert_eq!(
+        received_messages
+            .iter()
+            .filter(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(_)))
+            .count(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_vhost_baseline_reports_only_differing_hosts() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::headers(contains(("host", "admin.example.com"))))
+            .respond_with(responders::status_code(200).body("admin panel")),
+    );
+    server.expect(
+        Expectation::matching(request::headers(not(contains((
+            "host",
+            "admin.example.com",
+        )))))
+        .times(2) // baseline + "www"
+        .respond_with(responders::status_code(200).body("default site")),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+    let words = vec!["admin".to_string(), "www".to_string()];
+
+    start_scan(
+        client,
+        base_url,
+        words,
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            headers: vec!["Host: FUZZ.example.com".to_string()],
+            vhost_baseline: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut received_messages = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        received_messages.push(msg);
+    }
+
+    let found: Vec<_> = received_messages
+        .iter()
+        .filter_map(|e| match e {
+            dirnutek::ScanEvent::FoundUrl(s) => Some(s.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(found.len(), 1, "Found: {:?}", found);
+    assert!(found[0].contains("admin"));
+    assert!(received_messages.iter().any(
+        |e| matches!(e, dirnutek::ScanEvent::Warning(s) if s.contains("Baseline response is [200] with 12 chars"))
+    ));
+}
+
+#[tokio::test]
+async fn test_dedupe_by_body_hash_reports_first_alias_only() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/index.php"))
+            .respond_with(responders::status_code(200).body("same page")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/index.html"))
+            .respond_with(responders::status_code(200).body("same page")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/other"))
+            .respond_with(responders::status_code(200).body("different page")),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+    let words = vec![
+        "index.php".to_string(),
+        "index.html".to_string(),
+        "other".to_string(),
+    ];
+
+    start_scan(
+        client,
+        base_url,
+        words,
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            dedupe_by_body_hash: true,
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
@@ -794,5 +855,2621 @@ async fn test_start_scan_with_custom_headers() {
         received_messages.push(msg);
     }
 
-    assert!(received_messages.iter().any(|e| matches!(e, dirnutek::ScanEvent::FoundUrl(s) if s.contains("[200 OK]"))));
+    let found: Vec<_> = received_messages
+        .iter()
+        .filter_map(|e| match e {
+            dirnutek::ScanEvent::FoundUrl(s) => Some(s.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(found.len(), 2, "Found: {:?}", found);
+    assert!(found[0].contains("/index.php"));
+    assert!(found[1].contains("/other"));
+    assert!(received_messages.iter().any(|e| matches!(
+        e,
+        dirnutek::ScanEvent::Warning(s) if s.contains("/index.html has the same response as")
+    )));
+}
+
+#[tokio::test]
+async fn test_request_id_header_is_sent_and_reported() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/first")),
+            Box::new(request::headers(contains(("x-scan-id", "1")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/second")),
+            Box::new(request::headers(contains(("x-scan-id", "2")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+    let words = vec!["first".to_string(), "second".to_string()];
+
+    start_scan(
+        client,
+        base_url,
+        words,
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            request_id_header: Some("X-Scan-Id".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(s) = msg {
+            found.push(s.to_string());
+        }
+    }
+    assert_eq!(found.len(), 2, "Found: {:?}", found);
+    assert!(found[0].ends_with("first [0W, 0C, 0L] [X-Scan-Id: 1]"), "{}", found[0]);
+    assert!(found[1].ends_with("second [0W, 0C, 0L] [X-Scan-Id: 2]"), "{}", found[1]);
+}
+
+#[tokio::test]
+async fn test_adapt_to_waf_lowers_concurrency() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method("GET"))
+            .times(2) // preflight + "admin"
+            .respond_with(
+                responders::status_code(200)
+                    .insert_header("CF-RAY", "8a1b2c3d4e5f-AMS")
+                    .body("ok"),
+            ),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 10,
+            max_depth: 1,
+            delay: Some(1),
+            detect_waf: true,
+            adapt_to_waf: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut warnings = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::Warning(s) = msg {
+            warnings.push(s);
+        }
+    }
+    assert!(
+        warnings.iter().any(|w| w.contains("behind Cloudflare")),
+        "Warnings: {:?}",
+        warnings
+    );
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w == "Adapting to the WAF: concurrency 2, 500 ms delay between requests."),
+        "Warnings: {:?}",
+        warnings
+    );
+}
+
+#[tokio::test]
+async fn test_custom_fuzz_keyword_keeps_literal_fuzz() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/search")),
+            Box::new(request::query(matches("^q=FUZZ&id=42$"))),
+            Box::new(request::headers(contains(("x-token", "42")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/search?q=FUZZ&id=§").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["42".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            fuzz_mode: FuzzMode::Parameter,
+            headers: vec!["X-Token: §".to_string()],
+            fuzz_keyword: Some("§".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(s) = msg {
+            found.push(s.to_string());
+        }
+    }
+    assert_eq!(found.len(), 1, "Found: {:?}", found);
+    assert!(found[0].contains("/search?q=FUZZ&id=42"), "{}", found[0]);
+}
+
+#[tokio::test]
+async fn test_max_url_length_stops_runaway_recursion() {
+    // Every path exists, so each found directory would be recursed into forever.
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method("GET"))
+            .times(1..)
+            .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let max_url_length = base_url.as_str().len() + 15; // room for 3 "abcd/" segments
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["abcd".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 100,
+            max_url_length: Some(max_url_length),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    let mut warnings = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(s) => found.push(s.to_string()),
+            dirnutek::ScanEvent::Warning(s) => warnings.push(s),
+            _ => {}
+        }
+    }
+    assert_eq!(found.len(), 4, "Found: {:?}", found);
+    assert_eq!(warnings.len(), 1, "Warnings: {:?}", warnings);
+    assert!(warnings[0].contains("abcd/abcd/abcd/abcd/: URL is longer than"));
+}
+
+#[tokio::test]
+async fn test_extension_bypass_requests_variants() {
+    let server = Server::run();
+    for (path, status) in [
+        ("/index.php", 404),
+        ("/index.php%00.jpg", 404),
+        ("/index.php;.jpg", 200),
+        ("/index.php.", 404),
+        ("/index.php%20", 404),
+        ("/admin", 404),
+    ] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(status)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["index.php".to_string(), "admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            extension_bypass: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    let mut total_words = 0;
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(s) => found.push(s.to_string()),
+            dirnutek::ScanEvent::ScanStarted { total_words: total } => total_words = total,
+            _ => {}
+        }
+    }
+    assert_eq!(total_words, 6);
+    assert_eq!(found.len(), 1, "Found: {:?}", found);
+    assert!(found[0].contains("/index.php;.jpg"), "{}", found[0]);
+}
+
+#[tokio::test]
+async fn test_follow_meta_refresh_requests_same_origin_target() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/old"))
+            .respond_with(
+                responders::status_code(200)
+                    .insert_header("Content-Type", "text/html")
+                    .body(r#"<meta http-equiv="refresh" content="0;url=/hidden/page">"#),
+            ),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/away"))
+            .respond_with(
+                responders::status_code(200)
+                    .insert_header("Content-Type", "text/html")
+                    .body(r#"<script>window.location = "https://elsewhere.example/";</script>"#),
+            ),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/hidden/page"))
+            .respond_with(responders::status_code(200).body("secret")),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["old".to_string(), "away".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            follow_meta_refresh: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(s) = msg {
+            found.push(s.to_string());
+        }
+    }
+    assert_eq!(found.len(), 3, "Found: {:?}", found);
+    assert!(found[0].contains("old -> http://127.0.0.1"), "{}", found[0]);
+    assert!(found[0].contains("/hidden/page (client redirect)"), "{}", found[0]);
+    assert!(found[1].contains("away -> https://elsewhere.example/ (client redirect)"));
+    assert!(found[2].contains("/hidden/page [1W, 6C, 1L]"), "{}", found[2]);
+}
+
+#[tokio::test]
+async fn test_baseline_url_hides_similar_responses() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/does-not-exist"))
+            .respond_with(responders::status_code(200).body("Page /does-not-exist not found")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/a"))
+            .respond_with(responders::status_code(200).body("Page /a not found")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(200).body("<html><body><h1>Admin panel</h1><form>Please log in to continue</form></body></html>")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/old"))
+            .respond_with(responders::status_code(403).body("Page /old not found")),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["a".to_string(), "admin".to_string(), "old".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            baseline_url: Some("/does-not-exist".to_string()),
+            baseline_threshold: 15,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(s) = msg {
+            found.push(s.to_string());
+        }
+    }
+    found.sort();
+    assert_eq!(found.len(), 2, "Found: {:?}", found);
+    assert!(found[0].starts_with("[200 OK]") && found[0].contains("/admin"), "{}", found[0]);
+    assert!(found[1].starts_with("[403 Forbidden]") && found[1].contains("/old"), "{}", found[1]);
+}
+
+#[tokio::test]
+async fn test_burst_pauses_between_bursts() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method("GET"))
+            .times(3)
+            .respond_with(responders::status_code(404)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+    let pause = std::time::Duration::from_millis(300);
+
+    let started = std::time::Instant::now();
+    start_scan(
+        client,
+        base_url,
+        vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 2,
+            max_depth: 1,
+            burst: Some(dirnutek::Burst { requests: 2, pause }),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(started.elapsed() >= pause);
+
+    let mut events = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if !matches!(msg, dirnutek::ScanEvent::RequestCompleted { .. }) {
+            events.push(msg);
+        }
+    }
+    assert_eq!(
+        events,
+        vec![
+            dirnutek::ScanEvent::ScanStarted { total_words: 3 },
+            dirnutek::ScanEvent::BurstPaused { pause },
+            dirnutek::ScanEvent::BurstResumed,
+            dirnutek::ScanEvent::ScanFinished,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_recursion_words_are_used_below_depth_zero() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/dir"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/big"))
+            .respond_with(responders::status_code(404)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/dir/small"))
+            .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["dir".to_string(), "big".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 2,
+            recursion_words: Some(Arc::new(vec!["small".to_string()])),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(s) = msg {
+            found.push(s.to_string());
+        }
+    }
+    assert_eq!(
+        found,
+        vec![
+            format!("[200 OK] {} [0W, 0C, 0L]", server.url("/dir")),
+            format!("[200 OK] {} [0W, 0C, 0L]", server.url("/dir/small")),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_show_word_reports_the_requested_word() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/search")),
+            Box::new(request::query(eq("q=a+b"))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/search?q=FUZZ").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["a b".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            fuzz_mode: FuzzMode::Parameter,
+            show_word: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(s) = msg {
+            found.push(s.to_string());
+        }
+    }
+    assert_eq!(found.len(), 1, "Found: {:?}", found);
+    assert!(found[0].ends_with("/search?q=a+b [0W, 0C, 0L] [word: a b]"), "{}", found[0]);
+}
+
+#[tokio::test]
+async fn test_max_total_bytes_switches_to_head() {
+    let server = Server::run();
+    for path in ["/a", "/b"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200).body("8 bytes!")),
+        );
+    }
+    server.expect(
+        Expectation::matching(request::method_path("HEAD", "/c"))
+            .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+    let options = ScanConfig {
+        max_total_bytes: Some(10),
+        ..Default::default()
+    };
+    let downloaded_bytes = options.downloaded_bytes.clone();
+
+    start_scan(
+        client,
+        base_url,
+        vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            ..options
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut warnings = Vec::new();
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::Warning(s) => warnings.push(s),
+            dirnutek::ScanEvent::FoundUrl(s) => found.push(s.to_string()),
+            _ => {}
+        }
+    }
+    assert_eq!(downloaded_bytes.load(std::sync::atomic::Ordering::SeqCst), 16);
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert!(warnings[0].starts_with("Downloaded 16 bytes, more than the budget of 10 bytes"));
+    assert_eq!(found.len(), 3, "{:?}", found);
+    // The budget is spent, so the size of /c is unknown.
+    assert!(found[2].ends_with("/c [?W, ?C, ?L]"), "{}", found[2]);
+}
+
+/// Scans a site where `/a/a` and `/b/b` exist below the directories `/a` and `/b`,
+/// returning the paths of the hits in the order they were reported. The trailing
+/// missing words make the recursion of each directory finish before the next one
+/// is dequeued.
+async fn recursion_order(depth_first: bool) -> Vec<String> {
+    let server = Server::run();
+    for path in ["/a", "/b", "/a/a", "/b/b"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200)),
+        );
+    }
+    for path in ["/y", "/z", "/a/b", "/a/y", "/a/z", "/b/a", "/b/y", "/b/z"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(404)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        ["a", "b", "y", "z"].map(String::from).to_vec(),
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 2,
+            depth_first,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(Url::parse(&finding.url).unwrap().path().to_string());
+        }
+    }
+    found
+}
+
+#[tokio::test]
+async fn test_depth_first_scans_the_last_found_directory_first() {
+    assert_eq!(recursion_order(false).await, ["/a", "/b", "/a/a", "/b/b"]);
+    assert_eq!(recursion_order(true).await, ["/a", "/b", "/b/b", "/a/a"]);
+}
+
+#[tokio::test]
+async fn test_cookie_jar_merges_header_cookies() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/login"))
+            .respond_with(responders::status_code(200).insert_header("Set-Cookie", "session=new; Path=/")),
+    );
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/account")),
+            Box::new(request::headers(contains(("cookie", "session=new; theme=dark")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        ["login", "account"].map(String::from).to_vec(),
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            headers: vec!["Cookie: session=old; theme=dark".to_string()],
+            cookie_jar: Some(Arc::new(dirnutek::cookies::CookieJar::default())),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(Url::parse(&finding.url).unwrap().path().to_string());
+        }
+    }
+    assert_eq!(found, vec!["/login", "/account"]);
+}
+
+#[tokio::test]
+async fn test_cookie_jar_sends_rotated_cookie() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/login"))
+            .respond_with(responders::status_code(200).insert_header("Set-Cookie", "session=1; Path=/")),
+    );
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/account")),
+            Box::new(request::headers(contains(("cookie", "session=1")))),
+        ]))
+        .respond_with(responders::status_code(200).insert_header("Set-Cookie", "session=2; Path=/")),
+    );
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/admin")),
+            Box::new(request::headers(contains(("cookie", "session=2")))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        ["login", "account", "admin"].map(String::from).to_vec(),
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            cookie_jar: Some(Arc::new(dirnutek::cookies::CookieJar::default())),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut statuses = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            statuses.push((Url::parse(&finding.url).unwrap().path().to_string(), finding.status));
+        }
+    }
+    assert_eq!(
+        statuses,
+        vec![
+            ("/login".to_string(), 200),
+            ("/account".to_string(), 200),
+            ("/admin".to_string(), 200),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_raw_framing_sends_overridden_headers() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"0\r\n\r\n") {
+            let n = stream.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&format!("http://{}/login", addr)).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["1".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            http_method: HttpMethod::POST,
+            data: Some("q=FUZZ".to_string()),
+            raw_framing: Some(dirnutek::raw::RawFraming {
+                content_length: Some(40),
+                chunked: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let request = server.await.unwrap();
+    assert!(request.starts_with("POST /login HTTP/1.1\r\n"), "{}", request);
+    assert!(request.contains("\r\nContent-Length: 40\r\n"), "{}", request);
+    assert!(request.contains("\r\nTransfer-Encoding: chunked\r\n"), "{}", request);
+    assert!(request.ends_with("\r\n\r\n3\r\nq=1\r\n0\r\n\r\n"), "{}", request);
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding.to_string());
+        }
+    }
+    assert_eq!(found, vec![format!("[200 OK] http://{}/login [1W, 5C, 1L]", addr)]);
+}
+
+/// Scans a raw framing server that sends `response` and then keeps the connection
+/// open, and returns the reported hits and errors.
+async fn scan_stalled_raw_server(
+    response: &'static [u8],
+    framing: dirnutek::raw::RawFraming,
+) -> (Vec<String>, Vec<String>) {
+    use tokio::io::AsyncWriteExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream.write_all(response).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    });
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["a".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 1,
+            raw_framing: Some(framing),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (mut found, mut errors) = (Vec::new(), Vec::new());
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(finding) => found.push(finding.to_string()),
+            dirnutek::ScanEvent::ErrorOccurred(e) => errors.push(e),
+            _ => {}
+        }
+    }
+    (found, errors)
+}
+
+#[tokio::test]
+async fn test_raw_framing_caps_the_response_size() {
+    let started = std::time::Instant::now();
+    let (found, errors) = scan_stalled_raw_server(
+        b"HTTP/1.1 200 OK\r\n\r\none two three four",
+        dirnutek::raw::RawFraming {
+            timeout: Some(std::time::Duration::from_secs(3)),
+            max_response_size: Some(7),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert!(found[0].ends_with("/a [2W, 7C, 1L]"), "{}", found[0]);
+    assert!(started.elapsed() < std::time::Duration::from_secs(3));
+}
+
+#[tokio::test]
+async fn test_raw_framing_uses_the_timeout() {
+    let started = std::time::Instant::now();
+    let (found, errors) = scan_stalled_raw_server(
+        b"HTTP/1.1 200 OK\r\n",
+        dirnutek::raw::RawFraming {
+            timeout: Some(std::time::Duration::from_millis(300)),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(found.is_empty(), "{:?}", found);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(errors[0].contains("timed out"), "{}", errors[0]);
+    assert!(started.elapsed() < std::time::Duration::from_secs(3));
+}
+
+/// Serves each `(path, response)` once, whatever the request method, scans for the
+/// paths with `options` and returns the reported findings.
+async fn scan_responses(
+    responses: Vec<(&'static str, responders::ResponseBuilder)>,
+    options: ScanConfig,
+) -> Vec<dirnutek::Finding> {
+    let server = Server::run();
+    let mut words = Vec::new();
+    for (path, response) in responses {
+        server.expect(Expectation::matching(request::path(path)).respond_with(response));
+        words.push(path.trim_start_matches('/').to_string());
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        words,
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            ..options
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding);
+        }
+    }
+    found
+}
+
+/// The URL paths of `findings`.
+fn found_paths(findings: Vec<dirnutek::Finding>) -> Vec<String> {
+    findings
+        .iter()
+        .map(|finding| Url::parse(&finding.url).unwrap().path().to_string())
+        .collect()
+}
+
+/// Scans `/admin`, `/login` and `/missing`, whose bodies mention a dashboard, a login
+/// form and a not-found message, and returns the paths of the reported hits.
+async fn scan_with_body_regex(options: ScanConfig) -> Vec<String> {
+    let responses = [
+        ("/admin", "Welcome to the admin dashboard"),
+        ("/login", "Please log in"),
+        ("/missing", "Page not found"),
+    ]
+    .into_iter()
+    .map(|(path, body)| (path, responders::status_code(200).body(body)))
+    .collect();
+    found_paths(scan_responses(responses, options).await)
+}
+
+#[tokio::test]
+async fn test_match_regex_keeps_matching_bodies() {
+    let found = scan_with_body_regex(ScanConfig {
+        match_regex: Some(regex::Regex::new("admin|log in").unwrap()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, ["/admin", "/login"]);
+}
+
+#[tokio::test]
+async fn test_filter_regex_drops_matching_bodies() {
+    let found = scan_with_body_regex(ScanConfig {
+        filter_regex: Some(regex::Regex::new("(?i)not found").unwrap()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, ["/admin", "/login"]);
+
+    let found = scan_with_body_regex(ScanConfig {
+        match_regex: Some(regex::Regex::new("a").unwrap()),
+        filter_regex: Some(regex::Regex::new("dashboard").unwrap()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, ["/login", "/missing"]);
+}
+
+#[tokio::test]
+async fn test_char_range_combines_with_exact_filters() {
+    let server = Server::run();
+    for (path, body) in [
+        ("/short", "tiny"),
+        ("/soft404a", "Sorry, page not found"),
+        ("/soft404b", "Sorry, page not found!"),
+        ("/real", "This is the real administration page"),
+    ] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200).body(body)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        ["short", "soft404a", "soft404b", "real"].map(String::from).to_vec(),
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            exclude_exact_chars: Some(vec![21]),
+            char_range: dirnutek::CountRange {
+                min: Some(10),
+                max: Some(30),
+            },
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(Url::parse(&finding.url).unwrap().path().to_string());
+        }
+    }
+    // /short is below the range, /real above it and /soft404a has an excluded size.
+    assert_eq!(found, ["/soft404b"]);
+}
+
+/// Scans five paths with word-count, regex and status match conditions combined by
+/// `match_mode`, returning the paths of the reported hits.
+async fn scan_with_match_mode(match_mode: dirnutek::MatchMode) -> Vec<String> {
+    let server = Server::run();
+    for (path, status, body) in [
+        ("/a", 200, "admin panel"),
+        ("/b", 200, "x"),
+        ("/c", 200, "admin"),
+        ("/d", 200, "foo bar"),
+        ("/e", 403, "nothing here"),
+    ] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(status).body(body)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        ["a", "b", "c", "d", "e"].map(String::from).to_vec(),
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            include_status: Some(HashSet::from([403])),
+            exact_words: Some(vec![1]),
+            match_regex: Some(regex::Regex::new("admin").unwrap()),
+            match_mode,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(Url::parse(&finding.url).unwrap().path().to_string());
+        }
+    }
+    found
+}
+
+#[tokio::test]
+async fn test_match_mode_all_requires_every_condition() {
+    // Only /c has one word and mentions admin, but it is not a 403.
+    assert!(scan_with_match_mode(dirnutek::MatchMode::All).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_match_mode_any_reports_each_passing_condition() {
+    assert_eq!(
+        scan_with_match_mode(dirnutek::MatchMode::Any).await,
+        ["/a", "/b", "/c", "/e"]
+    );
+}
+
+/// Scans `/a`, which redirects to `/b` and then `/c`, following at most
+/// `follow_redirects` hops, and returns the single finding.
+async fn scan_redirect_chain(follow_redirects: usize) -> (Server, dirnutek::Finding) {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/a"))
+            .respond_with(responders::status_code(301).insert_header("Location", "/b")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/b"))
+            .respond_with(responders::status_code(302).insert_header("Location", "c")),
+    );
+    if follow_redirects > 1 {
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/c"))
+                .respond_with(responders::status_code(200).body("done")),
+        );
+    }
+
+    let client = Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["a".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            follow_redirects: Some(follow_redirects),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding);
+        }
+    }
+    assert_eq!(found.len(), 1);
+    (server, found.remove(0))
+}
+
+#[tokio::test]
+async fn test_follow_redirects_reports_the_chain() {
+    let (server, finding) = scan_redirect_chain(5).await;
+    assert_eq!(
+        finding.to_string(),
+        format!(
+            "[200 OK] {} -> {} -> {} [1W, 4C, 1L]",
+            server.url("/a"),
+            server.url("/b"),
+            server.url("/c")
+        )
+    );
+    let statuses: Vec<u16> = finding.redirect_chain.iter().map(|hop| hop.status).collect();
+    assert_eq!(statuses, [301, 302, 200]);
+    assert_eq!(finding.redirects, 2);
+}
+
+#[tokio::test]
+async fn test_follow_redirects_stops_at_the_hop_limit() {
+    let (server, finding) = scan_redirect_chain(1).await;
+    assert_eq!(
+        finding.to_string(),
+        format!(
+            "[302 Found] {} -> {} -> {} [0W, 0C, 0L]",
+            server.url("/a"),
+            server.url("/b"),
+            server.url("/c")
+        )
+    );
+    assert_eq!(finding.redirects, 1);
+}
+
+#[tokio::test]
+async fn test_follow_redirects_skips_large_hop_bodies_and_caps_the_final_body() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // /a and /b redirect and announce 100 MiB bodies that are never sent: reading
+    // either one would stall until the client timeout. /c has a body to truncate.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0; 1024];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+                let location = if request.starts_with("GET /a ") {
+                    Some("/b")
+                } else if request.starts_with("GET /b ") {
+                    Some("/c")
+                } else {
+                    None
+                };
+                match location {
+                    Some(location) => {
+                        let head = format!(
+                            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 104857600\r\n\r\n",
+                            location
+                        );
+                        let _ = stream.write_all(head.as_bytes()).await;
+                        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    }
+                    None => {
+                        let _ = stream
+                            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 18\r\nConnection: close\r\n\r\none two three four")
+                            .await;
+                    }
+                }
+            });
+        }
+    });
+
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(1))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["a".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            follow_redirects: Some(5),
+            max_response_size: Some(7),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding);
+        }
+    }
+    assert_eq!(found.len(), 1, "{:?}", found);
+    let statuses: Vec<u16> = found[0].redirect_chain.iter().map(|hop| hop.status).collect();
+    assert_eq!(statuses, [302, 302, 200]);
+    assert!(found[0].to_string().ends_with("/c [2W, 7C, 1L]"), "{}", found[0]);
+}
+
+#[tokio::test]
+async fn test_follow_redirects_drops_credentials_on_other_host() {
+    let other = Server::run();
+    other.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/landing")),
+            Box::new(request::headers(not(contains(key("authorization"))))),
+            Box::new(request::headers(not(contains(key("proxy-authorization"))))),
+            Box::new(request::headers(not(contains(key("cookie"))))),
+        ]))
+        .respond_with(responders::status_code(200).body("landing")),
+    );
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/login")),
+            Box::new(request::headers(contains(key("authorization")))),
+            Box::new(request::headers(contains(("cookie", "session=1")))),
+        ]))
+        .respond_with(
+            responders::status_code(302).insert_header("Location", other.url_str("/landing")),
+        ),
+    );
+
+    let client = Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["login".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            follow_redirects: Some(5),
+            basic_auth: Some(("admin".to_string(), "s3cr3t".to_string())),
+            headers: vec!["Proxy-Authorization: Basic cHJveHk6cHJveHk=".to_string()],
+            cookies: vec!["session=1".to_string()],
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding);
+        }
+    }
+    assert_eq!(found.len(), 1, "{:?}", found);
+    let statuses: Vec<u16> = found[0].redirect_chain.iter().map(|hop| hop.status).collect();
+    assert_eq!(statuses, [302, 200]);
+}
+
+#[tokio::test]
+async fn test_auto_filter_hides_calibrated_soft_404() {
+    let server = Server::run();
+    let soft_404 = "Sorry, we could not find that page.";
+    server.expect(
+        Expectation::matching(request::path(matches("^/dirnutek-calibration-")))
+            .times(dirnutek::AUTO_FILTER_PROBES)
+            .respond_with(responders::status_code(200).body(soft_404)),
+    );
+    for (path, body) in [("/admin", "Admin area"), ("/missing", soft_404)] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200).body(body)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        ["admin", "missing"].map(String::from).to_vec(),
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            auto_filter: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    let mut warnings = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(finding) => found.push(finding.to_string()),
+            dirnutek::ScanEvent::Warning(warning) => warnings.push(warning),
+            _ => {}
+        }
+    }
+    assert_eq!(found, vec![format!("[200 OK] {} [2W, 10C, 1L]", server.url("/admin"))]);
+    assert_eq!(
+        warnings,
+        ["Auto-filter calibrated [200] with 7W, 35C, 1L; matching responses will be hidden."]
+    );
+}
+
+/// Scans `/admin`, which requires the given `Authorization` header, with
+/// `--auth admin:s3cr3t` and the user `headers`. Returns the statuses of the findings
+/// and the warnings.
+async fn scan_with_basic_auth(
+    authorization: &str,
+    headers: Vec<String>,
+) -> (Vec<u16>, Vec<String>) {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/admin")),
+            Box::new(request::headers(contains(("authorization", authorization.to_string())))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            headers,
+            basic_auth: Some(("admin".to_string(), "s3cr3t".to_string())),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut statuses = Vec::new();
+    let mut warnings = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(finding) => statuses.push(finding.status),
+            dirnutek::ScanEvent::Warning(warning) => warnings.push(warning),
+            _ => {}
+        }
+    }
+    (statuses, warnings)
+}
+
+#[tokio::test]
+async fn test_basic_auth_sends_credentials() {
+    let (statuses, warnings) = scan_with_basic_auth("Basic YWRtaW46czNjcjN0", vec![]).await;
+    assert_eq!(statuses, [200]);
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+#[tokio::test]
+async fn test_basic_auth_yields_to_authorization_header() {
+    let (statuses, warnings) = scan_with_basic_auth(
+        "Bearer token",
+        vec!["Authorization: Bearer token".to_string()],
+    )
+    .await;
+    assert_eq!(statuses, [200]);
+    assert_eq!(
+        warnings,
+        ["An Authorization header is set; it is sent instead of the --auth credentials."]
+    );
+}
+
+async fn scan_with_retries(retries: usize, responses: Vec<u16>) -> Vec<u16> {
+    let server = Server::run();
+    let attempts = responses.len();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .times(attempts)
+            .respond_with(responders::cycle(
+                responses
+                    .into_iter()
+                    .map(|status| {
+                        Box::new(responders::status_code(status)) as Box<dyn responders::Responder>
+                    })
+                    .collect(),
+            )),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            retries,
+            retry_backoff: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut statuses = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            statuses.push(finding.status);
+        }
+    }
+    statuses
+}
+
+#[tokio::test]
+async fn test_retries_recover_from_server_errors() {
+    assert_eq!(scan_with_retries(3, vec![503, 503, 200]).await, vec![200]);
+}
+
+#[tokio::test]
+async fn test_ttfb_leaves_out_retry_backoff() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .times(2)
+            .respond_with(responders::cycle(vec![
+                Box::new(responders::status_code(503)),
+                Box::new(responders::status_code(200)),
+            ])),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            retries: 1,
+            retry_backoff: 600,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding);
+        }
+    }
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert!(found[0].ttfb_ms < 600, "{:?}", found[0]);
+}
+
+#[tokio::test]
+async fn test_retries_skip_client_errors() {
+    // A 403 is reported after the first attempt; a second request would not match.
+    assert_eq!(scan_with_retries(3, vec![403]).await, vec![403]);
+}
+
+#[tokio::test]
+async fn test_every_redirect_status_reports_its_resolved_target() {
+    for status in [301, 302, 303, 307, 308] {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/docs/old"))
+                .respond_with(
+                    responders::status_code(status)
+                        .insert_header("Location", "new/")
+                        .body("Moved elsewhere"),
+                ),
+        );
+
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let base_url = Url::parse(&server.url("/docs/").to_string()).unwrap();
+        let (tx, mut rx) = mpsc::channel(100);
+
+        start_scan(
+            client,
+            base_url,
+            vec!["old".to_string()],
+            tx,
+            TEST_CONTROL_CHANNEL.1.resubscribe(),
+            ScanConfig {
+                concurrency: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut found = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+                found.push(finding);
+            }
+        }
+        assert_eq!(found.len(), 1, "{}", status);
+        assert_eq!(found[0].status, status);
+        assert_eq!(
+            found[0].redirect_target.as_deref(),
+            Some(server.url("/docs/new/").to_string().as_str()),
+            "{}",
+            status
+        );
+        assert_eq!((found[0].words, found[0].chars, found[0].lines), (Some(0), Some(0), Some(0)), "{}", status);
+    }
+}
+
+#[tokio::test]
+async fn test_ttfb_range_reports_slow_responses_with_timings() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Delays the response head of /slow; both bodies arrive right after the head.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0; 1024];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                if String::from_utf8_lossy(&buf[..n]).starts_with("GET /slow ") {
+                    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                }
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                    .await;
+            });
+        }
+    });
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["fast".to_string(), "slow".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 2,
+            ttfb_range: dirnutek::CountRange {
+                min: Some(200),
+                max: None,
+            },
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding);
+        }
+    }
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert!(found[0].url.ends_with("/slow"));
+    assert!(found[0].ttfb_ms >= 300, "{:?}", found[0]);
+    assert!(found[0].total_ms >= found[0].ttfb_ms, "{:?}", found[0]);
+}
+
+async fn scan_with_wordlist2(pitchfork: bool, requests: usize) -> (usize, Vec<String>) {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/login"))
+            .times(requests)
+            .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/login?user=FUZZ1&pass=FUZZ2").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string(), "root".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            fuzz_mode: FuzzMode::Parameter,
+            extra_wordlists: vec![Arc::new(vec![
+                "123456".to_string(),
+                "secret".to_string(),
+                "letmein".to_string(),
+            ])],
+            pitchfork,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut total_words = 0;
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::ScanStarted { total_words: total } => total_words = total,
+            dirnutek::ScanEvent::FoundUrl(finding) => {
+                let query = finding.url.split_once('?').unwrap().1.to_string();
+                found.push(query);
+            }
+            _ => {}
+        }
+    }
+    found.sort();
+    (total_words, found)
+}
+
+#[tokio::test]
+async fn test_wordlist2_clusterbomb_requests_every_combination() {
+    let (total_words, found) = scan_with_wordlist2(false, 6).await;
+    assert_eq!(total_words, 6);
+    assert_eq!(
+        found,
+        [
+            "user=admin&pass=123456",
+            "user=admin&pass=letmein",
+            "user=admin&pass=secret",
+            "user=root&pass=123456",
+            "user=root&pass=letmein",
+            "user=root&pass=secret",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_wordlist2_pitchfork_pairs_words_up_to_the_shorter_list() {
+    let (total_words, found) = scan_with_wordlist2(true, 2).await;
+    assert_eq!(total_words, 2);
+    assert_eq!(found, ["user=admin&pass=123456", "user=root&pass=secret"]);
+}
+
+#[tokio::test]
+async fn test_extensions_request_every_word_with_each_extension() {
+    let server = Server::run();
+    for path in ["/admin", "/admin.php", "/admin.html"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            extensions: vec!["php".to_string(), "html".to_string()],
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut total_words = 0;
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::ScanStarted { total_words: total } => total_words = total,
+            dirnutek::ScanEvent::FoundUrl(finding) => found.push(finding.url),
+            _ => {}
+        }
+    }
+    assert_eq!(total_words, 3);
+    assert_eq!(
+        found,
+        [
+            server.url("/admin").to_string(),
+            server.url("/admin.php").to_string(),
+            server.url("/admin.html").to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_form_data_is_sent_urlencoded() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("POST", "/login")),
+            Box::new(request::headers(contains((
+                "content-type",
+                "application/x-www-form-urlencoded",
+            )))),
+            Box::new(request::body(eq("user=admin&pass=p%40ss+word"))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/login").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["p@ss word".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            http_method: HttpMethod::POST,
+            form_data: Some(vec![
+                ("user".to_string(), "admin".to_string()),
+                ("pass".to_string(), "FUZZ".to_string()),
+            ]),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut statuses = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            statuses.push(finding.status);
+        }
+    }
+    assert_eq!(statuses, [200]);
+}
+
+#[tokio::test]
+async fn test_recursion_preview_lists_directories_without_descending() {
+    let server = Server::run();
+    for path in ["/a", "/b"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200)),
+        );
+    }
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/c"))
+            .respond_with(responders::status_code(404)),
+    );
+    for path in ["/a/a", "/a/b", "/a/c", "/b/a", "/b/b", "/b/c"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .times(0)
+                .respond_with(responders::status_code(404)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 5,
+            recursion_preview: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    let mut preview = None;
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(finding) => found.push(finding.url),
+            dirnutek::ScanEvent::RecursionPreview { directories, requests } => {
+                preview = Some((directories, requests))
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(found, [server.url("/a").to_string(), server.url("/b").to_string()]);
+    let (directories, requests) = preview.expect("no recursion preview");
+    assert_eq!(
+        directories,
+        [server.url("/a/").to_string(), server.url("/b/").to_string()]
+    );
+    assert_eq!(requests, 6);
+}
+
+#[tokio::test]
+async fn test_level_delay_pauses_between_recursion_levels() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/dir"))
+            .respond_with(responders::status_code(200)),
+    );
+    for path in ["/big", "/dir/dir", "/dir/big"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(404)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    let started = std::time::Instant::now();
+    start_scan(
+        client,
+        base_url,
+        vec!["dir".to_string(), "big".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 2,
+            max_depth: 2,
+            level_delay: Some((200, 200)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert!(started.elapsed() >= std::time::Duration::from_millis(200));
+    let mut events = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(finding) => events.push(finding.url),
+            dirnutek::ScanEvent::LevelPaused { depth, pause } => {
+                events.push(format!("pause before depth {} for {:?}", depth, pause))
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(
+        events,
+        [
+            server.url("/dir").to_string(),
+            "pause before depth 1 for 200ms".to_string()
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_cookies_are_fuzzed_and_kept_across_redirects() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/admin")),
+            Box::new(request::headers(contains(("cookie", "theme=dark; session=admin")))),
+        ]))
+        .respond_with(
+            responders::status_code(302)
+                .insert_header("Location", "/home")
+                .insert_header("Set-Cookie", "rotated=1; Path=/"),
+        ),
+    );
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("GET", "/home")),
+            Box::new(request::headers(contains((
+                "cookie",
+                "theme=dark; session=admin; rotated=1",
+            )))),
+        ]))
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            cookies: vec!["theme=dark".to_string(), "session=FUZZ".to_string()],
+            cookie_jar: Some(Arc::new(dirnutek::cookies::CookieJar::default())),
+            follow_redirects: Some(5),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut statuses = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(finding) => statuses.push(finding.status),
+            dirnutek::ScanEvent::ErrorOccurred(e) => panic!("{}", e),
+            _ => {}
+        }
+    }
+    assert_eq!(statuses, [200]);
+}
+
+/// The User-Agents of a scan of four words with `random_agent` seeded with `seed`.
+async fn user_agents_sent(seed: u64) -> Vec<String> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (agents_tx, mut agents_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = [0; 2048];
+            let n = stream.read(&mut buf).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+            let agent = request
+                .lines()
+                .find_map(|line| line.strip_prefix("user-agent: "))
+                .unwrap_or_default()
+                .to_string();
+            agents_tx.send(agent).unwrap();
+            let _ = stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+        }
+    });
+
+    let client = Client::builder().user_agent("dirnutek-test").build().unwrap();
+    let base_url = Url::parse(&format!("http://{}/", addr)).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        ["a", "b", "c", "d"].map(String::from).to_vec(),
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            random_agent: true,
+            rng: dirnutek::ScanRng::new(seed),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    while rx.recv().await.is_some() {}
+
+    let mut agents = Vec::new();
+    while let Ok(agent) = agents_rx.try_recv() {
+        agents.push(agent);
+    }
+    agents
+}
+
+#[tokio::test]
+async fn test_random_agent_is_reproducible_with_a_seed() {
+    let agents = user_agents_sent(7).await;
+    assert_eq!(agents.len(), 4);
+    assert!(
+        agents.iter().all(|agent| dirnutek::USER_AGENTS.contains(&agent.as_str())),
+        "{:?}",
+        agents
+    );
+    assert_eq!(user_agents_sent(7).await, agents);
+}
+
+#[tokio::test]
+async fn test_no_body_for_skips_bodies_of_listed_statuses() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/admin"))
+            .respond_with(responders::status_code(403).body("Forbidden: go away")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/login"))
+            .respond_with(responders::status_code(200).body("Please log in")),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string(), "login".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            no_body_for: HashSet::from([403]),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut counts = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            counts.push((finding.status, finding.words, finding.chars));
+        }
+    }
+    counts.sort();
+    assert_eq!(counts, [(200, Some(3), Some(13)), (403, Some(0), Some(0))]);
+}
+
+#[tokio::test]
+async fn test_recurse_status_limits_recursion_to_listed_statuses() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/open"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/moved"))
+            .respond_with(responders::status_code(301).insert_header("Location", "/elsewhere")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/open/open"))
+            .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/open/moved"))
+            .respond_with(responders::status_code(404)),
+    );
+
+    let client = Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["open".to_string(), "moved".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 2,
+            recurse_status: Some(HashSet::from([200])),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push((finding.status, finding.url));
+        }
+    }
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            (200, server.url("/open").to_string()),
+            (200, server.url("/open/open").to_string()),
+            (301, server.url("/moved").to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_no_recurse_paths_are_reported_but_not_scanned() {
+    let server = Server::run();
+    for path in ["/static", "/admin"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(200)),
+        );
+    }
+    for path in ["/admin/static", "/admin/admin"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .respond_with(responders::status_code(404)),
+        );
+    }
+    server.expect(
+        Expectation::matching(request::path(matches("^/static/")))
+            .times(0)
+            .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["static".to_string(), "admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 2,
+            no_recurse_paths: vec!["/static".to_string()],
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding.url);
+        }
+    }
+    assert_eq!(found, [server.url("/static").to_string(), server.url("/admin").to_string()]);
+}
+
+/// Scans /plain, /debug (with an `X-Debug-Token` header) and /session (with a
+/// `Set-Cookie` header) and returns the reported paths.
+async fn scan_with_header_filters(options: ScanConfig) -> Vec<String> {
+    let responses = vec![
+        ("/plain", responders::status_code(200)),
+        (
+            "/debug",
+            responders::status_code(200).insert_header("X-Debug-Token", "1f2e"),
+        ),
+        (
+            "/session",
+            responders::status_code(200).insert_header("Set-Cookie", "id=1"),
+        ),
+    ];
+    found_paths(scan_responses(responses, options).await)
+}
+
+#[tokio::test]
+async fn test_match_has_header_reports_responses_with_the_header() {
+    let found = scan_with_header_filters(ScanConfig {
+        match_has_headers: vec!["x-debug-token".to_string()],
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, ["/debug"]);
+}
+
+#[tokio::test]
+async fn test_filter_has_header_hides_responses_with_the_header() {
+    let found = scan_with_header_filters(ScanConfig {
+        filter_has_headers: vec!["set-cookie".to_string()],
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, ["/plain", "/debug"]);
+}
+
+/// Scans `/portal`, which redirects to another host, with `allow_offsite_recursion`,
+/// expecting `offsite_requests` below the redirect target. Returns the findings and
+/// warnings.
+async fn scan_offsite_redirect(
+    allow_offsite_recursion: bool,
+    offsite_requests: usize,
+) -> (Vec<(String, Option<String>)>, Vec<String>, String) {
+    let offsite = Server::run();
+    offsite.expect(
+        Expectation::matching(request::method_path("GET", "/portal/portal"))
+            .times(offsite_requests)
+            .respond_with(responders::status_code(404)),
+    );
+    // Another host name for the same address, so the host differs from the scanned one.
+    let offsite_url = offsite
+        .url("/portal/")
+        .to_string()
+        .replace("127.0.0.1", "localhost");
+
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/portal"))
+            .respond_with(responders::status_code(302).insert_header("Location", offsite_url.as_str())),
+    );
+
+    let client = Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["portal".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            max_depth: 2,
+            allow_offsite_recursion,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (mut found, mut warnings) = (Vec::new(), Vec::new());
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::FoundUrl(finding) => {
+                found.push((finding.url, finding.redirect_target))
+            }
+            dirnutek::ScanEvent::Warning(warning) => warnings.push(warning),
+            _ => {}
+        }
+    }
+    (found, warnings, offsite_url)
+}
+
+#[tokio::test]
+async fn test_recursion_stays_on_the_scanned_host() {
+    let (found, warnings, offsite_url) = scan_offsite_redirect(false, 0).await;
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert_eq!(found[0].1.as_deref(), Some(offsite_url.as_str()));
+    assert_eq!(
+        warnings,
+        [format!(
+            "Not recursing into {}: it is not on the scanned host. \
+             Use --allow-offsite-recursion to follow it.",
+            offsite_url
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_allow_offsite_recursion_scans_the_redirect_target() {
+    let (found, warnings, _) = scan_offsite_redirect(true, 1).await;
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+#[tokio::test]
+async fn test_response_times_are_reported_and_slow_requests_warned() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/slow")).respond_with(
+            responders::delay_and_then(
+                std::time::Duration::from_millis(100),
+                responders::status_code(200),
+            ),
+        ),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/fast"))
+            .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["slow".to_string(), "fast".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            slow_threshold: Some(std::time::Duration::from_millis(50)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (mut completed, mut slow_warnings, mut slow_ttfb) = (Vec::new(), Vec::new(), None);
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            dirnutek::ScanEvent::RequestCompleted { elapsed } => completed.push(elapsed),
+            dirnutek::ScanEvent::Warning(msg) if msg.starts_with("Slow response") => {
+                slow_warnings.push(msg)
+            }
+            dirnutek::ScanEvent::FoundUrl(finding) if finding.url.ends_with("/slow") => {
+                slow_ttfb = Some(finding.ttfb_ms);
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(completed.len(), 2);
+    assert!(completed.iter().any(|elapsed| elapsed.as_millis() >= 100), "{:?}", completed);
+    assert!(slow_ttfb.is_some_and(|ttfb| ttfb >= 100), "{:?}", slow_ttfb);
+    assert!(
+        slow_warnings.iter().any(|warning| warning.contains("/slow took")),
+        "{:?}",
+        slow_warnings
+    );
+}
+
+#[tokio::test]
+async fn test_conditional_body_is_only_posted_after_a_matching_probe() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/login"))
+            .respond_with(responders::status_code(200).body("<form method=post></form>")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/about"))
+            .respond_with(responders::status_code(200).body("About us")),
+    );
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("POST", "/login")),
+            Box::new(request::body(eq("token=login"))),
+        ]))
+        .times(1)
+        .respond_with(responders::status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("POST", "/about"))
+            .times(0)
+            .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["login".to_string(), "about".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            http_method: HttpMethod::POST,
+            data: Some("token=FUZZ".to_string()),
+            conditional_body: Some(regex::Regex::new("<form").unwrap()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push((finding.method, finding.url));
+        }
+    }
+    assert_eq!(found, [("POST".to_string(), server.url("/login").to_string())]);
+}
+
+/// Scans /page (HTML with a charset), /api (JSON in mixed case) and /logo (PNG) with
+/// `options` and returns the paths reported as found.
+async fn scan_with_content_type_filters(options: ScanConfig) -> Vec<String> {
+    let responses = [
+        ("/page", "text/html; charset=utf-8"),
+        ("/api", "Application/JSON"),
+        ("/logo", "image/png"),
+    ]
+    .into_iter()
+    .map(|(path, content_type)| {
+        let response = responders::status_code(200).insert_header("Content-Type", content_type);
+        (path, response)
+    })
+    .collect();
+    found_paths(scan_responses(responses, options).await)
+}
+
+#[tokio::test]
+async fn test_match_content_type_ignores_charset_and_case() {
+    let found = scan_with_content_type_filters(ScanConfig {
+        match_content_types: vec!["text/html".to_string(), "application/json".to_string()],
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, ["/page", "/api"]);
+}
+
+#[tokio::test]
+async fn test_filter_content_type_hides_matching_prefix() {
+    let found = scan_with_content_type_filters(ScanConfig {
+        filter_content_types: vec!["image/".to_string()],
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, ["/page", "/api"]);
+}
+
+#[tokio::test]
+async fn test_dedupe_by_body_hash_reports_every_head_response() {
+    let server = Server::run();
+    for path in ["/index.php", "/index.html"] {
+        server.expect(
+            Expectation::matching(request::method_path("HEAD", path)).respond_with(responders::status_code(200)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["index.php".to_string(), "index.html".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            http_method: HttpMethod::HEAD,
+            max_depth: 1,
+            dedupe_by_body_hash: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = 0;
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(_) = msg {
+            found += 1;
+        }
+    }
+    assert_eq!(found, 2);
+}
+
+#[tokio::test]
+async fn test_compress_body_gzip_sends_fuzzed_body_compressed() {
+    let server = Server::run();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(br#"{"user":"admin"}"#).unwrap();
+    let expected = encoder.finish().unwrap();
+    server.expect(
+        Expectation::matching(all_of(vec![
+            Box::new(request::method_path("POST", "/login")),
+            Box::new(request::headers(contains(("content-encoding", "gzip")))),
+            Box::new(request::body(expected)),
+        ]))
+        .times(1)
+        .respond_with(responders::status_code(200)),
+    );
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&server.url("/login").to_string()).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["admin".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            http_method: HttpMethod::POST,
+            max_depth: 1,
+            data: Some(r#"{"user":"FUZZ"}"#.to_string()),
+            compress_body: Some(dirnutek::BodyCompression::Gzip),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = 0;
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(_) = msg {
+            found += 1;
+        }
+    }
+    assert_eq!(found, 1);
+}
+
+/// Scans /admin with `head_first` and checks that the request method and the counts
+/// follow whether `options` needs the body.
+async fn scan_head_first(options: ScanConfig) {
+    let body_needed = options.match_regex.is_some();
+    let responses = vec![("/admin", responders::status_code(200).body("admin panel"))];
+    let found = scan_responses(
+        responses,
+        ScanConfig {
+            max_depth: 1,
+            head_first: true,
+            ..options
+        },
+    )
+    .await;
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].method, if body_needed { "GET" } else { "HEAD" });
+    // The size of a HEAD response is unknown, not zero.
+    let expected_chars = body_needed.then_some("admin panel".len());
+    assert_eq!(found[0].chars, expected_chars);
+    let counts = if body_needed {
+        "[2W, 11C, 1L]"
+    } else {
+        "[?W, ?C, ?L]"
+    };
+    assert!(found[0].to_string().ends_with(counts), "{}", found[0]);
+}
+
+#[tokio::test]
+async fn test_head_first_sends_only_head_without_body_filters() {
+    scan_head_first(ScanConfig::default()).await;
+}
+
+#[tokio::test]
+async fn test_head_first_sends_get_when_a_body_filter_is_set() {
+    scan_head_first(ScanConfig {
+        match_regex: Some(regex::Regex::new("admin").unwrap()),
+        ..Default::default()
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_host_and_path_keywords_take_words_of_their_own_lists() {
+    let server = Server::run();
+    for path in ["/admin", "/login"] {
+        server.expect(
+            Expectation::matching(request::method_path("GET", path))
+                .times(1)
+                .respond_with(responders::status_code(200)),
+        );
+    }
+
+    let client = Client::builder().build().unwrap();
+    let base_url = Url::parse(&format!("http://FUZZ1:{}/FUZZ2", server.addr().port())).unwrap();
+    let (tx, mut rx) = mpsc::channel(100);
+
+    start_scan(
+        client,
+        base_url,
+        vec!["localhost".to_string()],
+        tx,
+        TEST_CONTROL_CHANNEL.1.resubscribe(),
+        ScanConfig {
+            concurrency: 1,
+            fuzz_mode: FuzzMode::Subdomain,
+            extra_wordlists: vec![Arc::new(vec!["admin".to_string(), "login".to_string()])],
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut found = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        if let dirnutek::ScanEvent::FoundUrl(finding) = msg {
+            found.push(finding.url);
+        }
+    }
+    let port = server.addr().port();
+    assert_eq!(
+        found,
+        [
+            format!("http://localhost:{}/admin", port),
+            format!("http://localhost:{}/login", port)
+        ]
+    );
 }